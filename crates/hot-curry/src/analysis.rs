@@ -0,0 +1,141 @@
+use std::{
+    collections::HashSet,
+    fmt::{self, Display, Formatter},
+};
+
+use serde::Serialize;
+
+use crate::visitor::TeraVariableVisitor;
+
+fn sorted(set: &HashSet<String>) -> Vec<String> {
+    let mut values = set.iter().cloned().collect::<Vec<_>>();
+
+    values.sort();
+
+    values
+}
+
+/// What the template expects from the data file, and what it uses
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Analysis {
+    /// The data paths read by the template, like `jobs[].title`
+    pub variables: Vec<String>,
+    /// The data paths used in a `for` loop
+    pub iterables: Vec<String>,
+    /// The data paths used as a condition, or only used conditionally
+    pub optionals: Vec<String>,
+    /// The filters applied in the template
+    pub filters: Vec<String>,
+    /// The templates included with `{% include %}`
+    pub includes: Vec<String>,
+    /// The macros called in the template, like `macros::header`
+    pub macros: Vec<String>,
+    /// The data paths never read by the template
+    pub unused: Vec<String>,
+}
+
+impl Analysis {
+    #[must_use]
+    pub fn new(visitor: &TeraVariableVisitor, unused: Vec<String>) -> Self {
+        Self {
+            variables: sorted(visitor.idents()),
+            iterables: sorted(visitor.iterable_idents()),
+            optionals: sorted(&visitor.optional_idents()),
+            filters: sorted(visitor.filters()),
+            includes: sorted(visitor.includes()),
+            macros: sorted(visitor.macro_calls()),
+            unused,
+        }
+    }
+}
+
+/// The human readable version of the analysis, empty sections are omitted
+impl Display for Analysis {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let sections = [
+            ("Used variables", &self.variables),
+            ("Iterable variables", &self.iterables),
+            ("Potentially optional variables", &self.optionals),
+            ("Used filters", &self.filters),
+            ("Included templates", &self.includes),
+            ("Called macros", &self.macros),
+            ("Unused data", &self.unused),
+        ];
+
+        for (title, values) in sections {
+            if values.is_empty() {
+                continue;
+            }
+
+            writeln!(f, "{title}:")?;
+
+            for value in values {
+                writeln!(f, "\t- {value}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tera::Template;
+
+    use super::*;
+
+    fn analysis() -> Analysis {
+        let template = Template::new(
+            "",
+            None,
+            "{% import 'macros.html' as macros %}{{ name | upper }}{% if photo %}{{ photo }}{% endif %}\
+             {% for job in jobs %}{{ macros::job(title=job.title) }}{% endfor %}{% include 'footer.html' %}",
+        )
+        .unwrap();
+        let mut visitor = TeraVariableVisitor::new();
+
+        visitor.visit_template(&template);
+
+        Analysis::new(&visitor, vec!["email".to_string()])
+    }
+
+    #[test]
+    fn serializes_to_json() {
+        assert_eq!(
+            serde_json::to_value(analysis()).unwrap(),
+            json!({
+                "variables": ["jobs", "jobs[].title", "name", "photo"],
+                "iterables": ["jobs"],
+                "optionals": ["photo"],
+                "filters": ["upper"],
+                "includes": ["footer.html"],
+                "macros": ["macros::job"],
+                "unused": ["email"],
+            })
+        );
+    }
+
+    #[test]
+    fn displays_the_non_empty_sections() {
+        assert_eq!(
+            analysis().to_string(),
+            "Used variables:\n\t- jobs\n\t- jobs[].title\n\t- name\n\t- photo\n\
+             Iterable variables:\n\t- jobs\n\
+             Potentially optional variables:\n\t- photo\n\
+             Used filters:\n\t- upper\n\
+             Included templates:\n\t- footer.html\n\
+             Called macros:\n\t- macros::job\n\
+             Unused data:\n\t- email\n"
+        );
+        assert_eq!(
+            Analysis {
+                variables: vec!["name".to_string()],
+                ..Analysis::default()
+            }
+            .to_string(),
+            "Used variables:\n\t- name\n"
+        );
+        assert_eq!(Analysis::default().to_string(), "");
+    }
+}
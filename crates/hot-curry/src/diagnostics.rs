@@ -0,0 +1,235 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    path::{Component, Path, PathBuf},
+};
+
+use tera_visitor::source::{regions, RegionKind};
+
+/// A position in a source file, the line and the column starting at 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    #[must_use]
+    pub fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+
+    /// The position of a byte offset in the source
+    #[must_use]
+    pub fn from_offset(source: &str, offset: usize) -> Self {
+        let before = &source[..offset];
+
+        let line = before.matches('\n').count() + 1;
+
+        let column = before
+            .rfind('\n')
+            .map_or(before, |line_start| &before[line_start + 1..])
+            .chars()
+            .count()
+            + 1;
+
+        Self { line, column }
+    }
+
+    /// Parses the `line:column` format used by Tera (and pest) in its parsing errors
+    #[must_use]
+    pub fn parse(position: &str) -> Option<Self> {
+        let (line, column) = position.split_once(':')?;
+
+        Some(Self {
+            line: line.trim().parse().ok()?,
+            column: column.trim().parse().ok()?,
+        })
+    }
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// The path shown in the messages, without the `.` components, so `./render.html` joined to the `./` base
+/// path of the default config is shown as `render.html` instead of `././render.html`
+#[must_use]
+pub fn display_path(path: &Path) -> String {
+    let path = path
+        .components()
+        .filter(|component| *component != Component::CurDir)
+        .collect::<PathBuf>();
+
+    if path.as_os_str().is_empty() {
+        ".".to_string()
+    } else {
+        path.to_string_lossy().into_owned()
+    }
+}
+
+/// Renders the line at `position`, and the lines around it, with a caret under the column:
+///
+/// ```text
+///   --> source.yml:2:6
+///    |
+///  1 | name: Jane
+///  2 | bio: [x
+///    |      ^
+///  3 | jobs: 1
+/// ```
+#[must_use]
+pub fn code_frame(path: &str, source: &str, position: Position) -> String {
+    let lines = source.lines().collect::<Vec<_>>();
+
+    let first_line = position.line.saturating_sub(1).max(1);
+    let last_line = (position.line + 1).min(lines.len());
+
+    let gutter_width = last_line.max(position.line).to_string().len();
+
+    let mut frame = format!(
+        "{:gutter_width$}--> {path}:{position}\n{:gutter_width$} |\n",
+        "", ""
+    );
+
+    for line in first_line..=last_line {
+        frame.push_str(&format!(
+            "{line:>gutter_width$} | {}\n",
+            lines.get(line - 1).copied().unwrap_or_default()
+        ));
+
+        if line == position.line {
+            frame.push_str(&format!(
+                "{:gutter_width$} | {:caret_offset$}^\n",
+                "",
+                "",
+                caret_offset = position.column.saturating_sub(1)
+            ));
+        }
+    }
+
+    frame
+}
+
+/// Splits a source in words (letters, digits, and underscores) and single punctuation characters,
+/// with their byte offset, whitespaces and dashes are skipped so `{%- if` is the same as `{% if`
+fn tokenize(source: &str) -> Vec<(usize, &str)> {
+    let mut tokens = Vec::new();
+    let mut word_start = None;
+
+    for (offset, c) in source.char_indices() {
+        if c.is_alphanumeric() || c == '_' {
+            word_start.get_or_insert(offset);
+
+            continue;
+        }
+
+        if let Some(start) = word_start.take() {
+            tokens.push((start, &source[start..offset]));
+        }
+
+        if !c.is_whitespace() && c != '-' {
+            tokens.push((offset, &source[offset..offset + c.len_utf8()]));
+        }
+    }
+
+    if let Some(start) = word_start {
+        tokens.push((start, &source[start..]));
+    }
+
+    tokens
+}
+
+/// Finds the `nth` (starting at 0) occurrence of `needle` in the tags and the `{{ }}` blocks of a Tera source,
+/// ignoring the whitespaces and the trim markers, so `{{ job` is found in `{{-job` and `for job in jobs` in
+/// `{% for  job in jobs %}`, the text, the comments, and the content of the `{% raw %}` blocks are skipped
+#[must_use]
+pub fn find(source: &str, needle: &str, nth: usize) -> Option<Position> {
+    let needle = tokenize(needle)
+        .into_iter()
+        .map(|(_, token)| token)
+        .collect::<Vec<_>>();
+
+    if needle.is_empty() {
+        return None;
+    }
+
+    regions(source)
+        .into_iter()
+        .filter(|region| matches!(region.kind, RegionKind::Variable | RegionKind::Tag))
+        .flat_map(|region| {
+            let tokens = tokenize(region.text);
+
+            tokens
+                .windows(needle.len())
+                .filter(|window| {
+                    window
+                        .iter()
+                        .zip(&needle)
+                        .all(|((_, token), needle_token)| token == needle_token)
+                })
+                .map(|window| region.start + window[0].0)
+                .collect::<Vec<_>>()
+        })
+        .nth(nth)
+        .map(|offset| Position::from_offset(source, offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_needles_in_tags() {
+        let source = "{{ name }}\n{{- name -}}\n{{name}}\n{%  for job in   jobs %}{% endfor %}";
+
+        assert_eq!(find(source, "{{ name", 0), Some(Position::new(1, 1)));
+        assert_eq!(find(source, "{{ name", 1), Some(Position::new(2, 1)));
+        assert_eq!(find(source, "{{ name", 2), Some(Position::new(3, 1)));
+        assert_eq!(find(source, "{{ name", 3), None);
+        assert_eq!(
+            find(source, "for job in jobs", 0),
+            Some(Position::new(4, 5))
+        );
+    }
+
+    #[test]
+    fn skips_text_comments_and_raw_blocks() {
+        let source = "name\n{# {{ name }} #}\n{% raw %}{{ name }}{% endraw %}\n{{ name }}";
+
+        assert_eq!(find(source, "{{ name", 0), Some(Position::new(4, 1)));
+        assert_eq!(find(source, "name", 0), Some(Position::new(4, 4)));
+        assert_eq!(find(source, "", 0), None);
+    }
+
+    #[test]
+    fn matches_inside_a_single_tag() {
+        let source = "{{ a }}{{ b }}";
+
+        assert_eq!(find(source, "a }} {{ b", 0), None);
+    }
+
+    #[test]
+    fn displays_paths_without_current_dir_components() {
+        assert_eq!(
+            display_path(&Path::new("./").join("./render.html")),
+            "render.html"
+        );
+        assert_eq!(
+            display_path(Path::new("cv/./templates/../render.html")),
+            "cv/templates/../render.html"
+        );
+        assert_eq!(display_path(Path::new("./")), ".");
+    }
+
+    #[test]
+    fn renders_a_code_frame() {
+        let source = "name: Jane\nbio: [x\njobs: 1\n";
+
+        assert_eq!(
+            code_frame("source.yml", source, Position::new(2, 6)),
+            " --> source.yml:2:6\n  |\n1 | name: Jane\n2 | bio: [x\n  |      ^\n3 | jobs: 1\n"
+        );
+    }
+}
@@ -1,3 +1,10 @@
+use std::{fs, path::Path};
+
+use crate::{
+    diagnostics::{code_frame, display_path, Position},
+    validation::Problem,
+};
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("IO error: {0}")]
@@ -12,9 +19,18 @@ pub enum Error {
     #[error("Json error: {0}")]
     Json(#[from] serde_json::Error),
 
+    #[error("The resume doesn't follow the JSON Resume schema:{}", format_problems(.0))]
+    JsonResume(Vec<Problem>),
+
+    #[error("Front matter error: {0}")]
+    FrontMatter(String),
+
     #[error("Headless browser error: {0}")]
     HeadlessBrowser(String),
 
+    #[error("The template can't be formatted in place")]
+    LossyFormat(#[from] tera_visitor::printer::Loss),
+
     #[error("Url error: {0}")]
     Url(#[from] url::ParseError),
 
@@ -23,6 +39,137 @@ pub enum Error {
 
     #[error("Http request error: {0}")]
     HttpRequest(#[from] reqwest::Error),
+
+    /// An error in a file (the template, the data file, or the config file) at a known position
+    #[error("{path}:{position}: {message}")]
+    Located {
+        path: String,
+        position: Position,
+        message: String,
+    },
+}
+
+fn format_problems(problems: &[Problem]) -> String {
+    problems
+        .iter()
+        .map(|problem| format!("\n\t- {problem}"))
+        .collect()
+}
+
+/// Removes the ` at line 2 column 5` suffix the yaml, json, and toml errors end with
+fn strip_location(message: &str) -> &str {
+    message
+        .rfind(" at line ")
+        .map_or(message, |index| &message[..index])
+}
+
+/// Tera parsing errors embed a pest error like ` --> 2:8 [...] = expected an identifier`
+fn tera_position(err: &tera::Error) -> Option<(Position, String)> {
+    let mut source: Option<&dyn std::error::Error> = Some(err);
+
+    while let Some(err) = source {
+        let message = err.to_string();
+
+        if let Some((_, position)) = message.split_once("--> ") {
+            let position = Position::parse(position.lines().next().unwrap_or_default())?;
+
+            let message = message
+                .lines()
+                .find_map(|line| line.trim().strip_prefix("= "))
+                .unwrap_or_else(|| message.trim());
+
+            return Some((position, message.to_string()));
+        }
+
+        source = err.source();
+    }
+
+    None
+}
+
+impl Error {
+    /// The position of the error in the file it comes from, and the error message without the position
+    fn position(&self) -> Option<(Position, String)> {
+        match self {
+            Error::Yaml(err) => err.location().map(|location| {
+                (
+                    Position::new(location.line(), location.column()),
+                    strip_location(&err.to_string()).to_string(),
+                )
+            }),
+            Error::Json(err) if err.line() > 0 => Some((
+                Position::new(err.line(), err.column().max(1)),
+                strip_location(&err.to_string()).to_string(),
+            )),
+            Error::TomlDeserialization(err) => err.line_col().map(|(line, column)| {
+                (
+                    Position::new(line + 1, column + 1),
+                    strip_location(&err.to_string()).to_string(),
+                )
+            }),
+            Error::Tera(err) => tera_position(err),
+            _ => None,
+        }
+    }
+
+    /// Attaches the path of the file the error comes from, if the position of the error is known,
+    /// or to the problems of a JSON Resume file
+    #[must_use]
+    pub fn with_path(self, path: impl Into<String>) -> Self {
+        if let Error::JsonResume(problems) = self {
+            let path = path.into();
+
+            return Error::JsonResume(
+                problems
+                    .into_iter()
+                    .map(|problem| Problem {
+                        file: Some(path.clone()),
+                        ..problem
+                    })
+                    .collect(),
+            );
+        }
+
+        match self.position() {
+            Some((position, message)) => Error::Located {
+                path: path.into(),
+                position,
+                message,
+            },
+            None => self,
+        }
+    }
+
+    /// Attaches the path of the template that failed to parse when loading all the templates from `templates_path`
+    #[must_use]
+    pub fn with_template_path(self, templates_path: &Path) -> Self {
+        let path = match &self {
+            Error::Tera(err) => err
+                .to_string()
+                .split_once("Failed to parse \"")
+                .and_then(|(_, name)| name.split_once('"'))
+                .map(|(name, _)| display_path(&templates_path.join(name))),
+            _ => None,
+        };
+
+        match path {
+            Some(path) => self.with_path(path),
+            None => self,
+        }
+    }
+
+    /// The lines around the error, if its position is known and its file can be read
+    #[must_use]
+    pub fn code_frame(&self) -> Option<String> {
+        match self {
+            Error::Located { path, position, .. } => {
+                let source = fs::read_to_string(path).ok()?;
+
+                Some(code_frame(path, &source, *position))
+            }
+            _ => None,
+        }
+    }
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
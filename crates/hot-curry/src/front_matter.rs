@@ -0,0 +1,162 @@
+use std::cmp::Ordering;
+
+use serde_json::{Map, Value};
+
+use crate::errors::{Error, Result};
+
+/// The variable the markdown body is exposed as
+pub(crate) const CONTENT_KEY: &str = "content";
+
+/// Splits the document in its front matter, yaml between `---` lines or toml between `+++` lines,
+/// and its body. Documents without front matter are only made of a body.
+pub(crate) fn split(source: &str) -> (Option<(&str, &str)>, &str) {
+    let mut lines = source.split_inclusive('\n');
+
+    let Some(delimiter) = lines
+        .next()
+        .map(str::trim_end)
+        .filter(|line| ["---", "+++"].contains(line))
+    else {
+        return (None, source);
+    };
+
+    let start = source.find('\n').map_or(source.len(), |index| index + 1);
+    let mut end = start;
+
+    for line in lines {
+        if line.trim_end() == delimiter {
+            return (
+                Some((delimiter, &source[start..end])),
+                &source[end + line.len()..],
+            );
+        }
+
+        end += line.len();
+    }
+
+    (None, source)
+}
+
+/// Parses a markdown document, the front matter fields are returned along with the body, as `content`
+pub fn parse(source: &str) -> Result<Value> {
+    let (front_matter, body) = split(source);
+
+    let fields = match front_matter {
+        // serde_yaml fails on empty documents
+        Some((_, front_matter)) if front_matter.trim().is_empty() => Value::Null,
+        // The opening delimiter is replaced by an empty line so the errors point to the right line
+        Some(("---", front_matter)) => serde_yaml::from_str(&format!("\n{front_matter}"))?,
+        Some((_, front_matter)) => toml::from_str(&format!("\n{front_matter}"))?,
+        None => Value::Null,
+    };
+
+    let mut fields = match fields {
+        Value::Object(fields) => fields,
+        Value::Null => Map::new(),
+        fields => {
+            return Err(Error::FrontMatter(format!(
+                "expected a table of fields, found {fields}"
+            )))
+        }
+    };
+
+    fields.insert(CONTENT_KEY.to_string(), Value::from(body.trim_start()));
+
+    Ok(Value::Object(fields))
+}
+
+/// Orders the front matter values, numbers and strings are compared together,
+/// and the documents without the value come last, in both orders
+#[must_use]
+pub fn compare(a: Option<&Value>, b: Option<&Value>, descending: bool) -> Ordering {
+    let ordering = |ordering: Ordering| {
+        if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    };
+
+    match (a, b) {
+        (Some(Value::Number(a)), Some(Value::Number(b))) => ordering(
+            a.as_f64()
+                .partial_cmp(&b.as_f64())
+                .unwrap_or(Ordering::Equal),
+        ),
+        (Some(Value::String(a)), Some(Value::String(b))) => ordering(a.cmp(b)),
+        (Some(a), Some(b)) => ordering(a.to_string().cmp(&b.to_string())),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn splits_the_front_matter() {
+        assert_eq!(
+            split("---\ntitle: A\n---\nBody\n"),
+            (Some(("---", "title: A\n")), "Body\n")
+        );
+        assert_eq!(
+            split("+++\r\ntitle = \"A\"\r\n+++\r\nBody"),
+            (Some(("+++", "title = \"A\"\r\n")), "Body")
+        );
+        assert_eq!(split("---\n---\n"), (Some(("---", "")), ""));
+    }
+
+    #[test]
+    fn keeps_the_documents_without_front_matter() {
+        assert_eq!(split("Body\n---\n"), (None, "Body\n---\n"));
+        // The front matter is never closed
+        assert_eq!(split("---\ntitle: A\n"), (None, "---\ntitle: A\n"));
+        assert_eq!(split(""), (None, ""));
+    }
+
+    #[test]
+    fn parses_the_fields_and_the_body() {
+        assert_eq!(
+            parse("---\ntitle: A\ntags: [x]\n---\n\nBody\n").unwrap(),
+            json!({ "title": "A", "tags": ["x"], "content": "Body\n" })
+        );
+        assert_eq!(
+            parse("+++\nstart = 2020\n+++\nBody").unwrap(),
+            json!({ "start": 2020, "content": "Body" })
+        );
+        assert_eq!(
+            parse("---\n---\nBody").unwrap(),
+            json!({ "content": "Body" })
+        );
+        assert_eq!(parse("Body").unwrap(), json!({ "content": "Body" }));
+    }
+
+    #[test]
+    fn rejects_front_matters_that_are_not_tables() {
+        assert!(matches!(
+            parse("---\n- a\n---\nBody"),
+            Err(Error::FrontMatter(_))
+        ));
+        assert!(matches!(
+            parse("---\ntitle: [\n---\nBody"),
+            Err(Error::Yaml(_))
+        ));
+    }
+
+    #[test]
+    fn compares_the_values() {
+        let (one, two, a) = (json!(1), json!(2.5), json!("a"));
+
+        assert_eq!(compare(Some(&one), Some(&two), false), Ordering::Less);
+        assert_eq!(compare(Some(&one), Some(&two), true), Ordering::Greater);
+        assert_eq!(compare(Some(&a), Some(&json!("b")), false), Ordering::Less);
+        // The documents without the value come last in both orders
+        assert_eq!(compare(None, Some(&one), false), Ordering::Greater);
+        assert_eq!(compare(None, Some(&one), true), Ordering::Greater);
+        assert_eq!(compare(None, None, true), Ordering::Equal);
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+use tera::Function;
+
+/// The `t(key="section.title")` function, returning the translation of the key in the language being rendered,
+/// nested keys are separated with dots
+#[derive(Debug, Clone, Default)]
+pub struct Translate {
+    translations: Value,
+}
+
+impl Translate {
+    #[must_use]
+    pub fn new(translations: Value) -> Self {
+        Self { translations }
+    }
+
+    fn translation(&self, key: &str) -> Option<&Value> {
+        self.translations.get(key).or_else(|| {
+            key.split('.')
+                .try_fold(&self.translations, |translations, key| {
+                    translations.get(key)
+                })
+        })
+    }
+}
+
+impl Function for Translate {
+    fn call(&self, args: &HashMap<String, Value>) -> tera::Result<Value> {
+        let key = args
+            .get("key")
+            .and_then(Value::as_str)
+            .ok_or_else(|| tera::Error::msg("The `t` function expects a string `key` argument"))?;
+
+        self.translation(key)
+            .cloned()
+            .ok_or_else(|| tera::Error::msg(format!("No translation found for `{key}`")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn t(args: Value) -> tera::Result<Value> {
+        let translate = Translate::new(json!({
+            "title": "Expériences",
+            "contact": { "email": "Courriel", "phone": "Téléphone" },
+            "skills.title": "Compétences",
+        }));
+
+        translate.call(&serde_json::from_value(args).unwrap())
+    }
+
+    #[test]
+    fn translates_the_keys() {
+        assert_eq!(t(json!({ "key": "title" })).unwrap(), "Expériences");
+        assert_eq!(
+            t(json!({ "key": "contact" })).unwrap()["phone"],
+            "Téléphone"
+        );
+    }
+
+    #[test]
+    fn translates_the_dotted_keys() {
+        assert_eq!(t(json!({ "key": "contact.email" })).unwrap(), "Courriel");
+        // A key with a dot is found before the nested keys
+        assert_eq!(t(json!({ "key": "skills.title" })).unwrap(), "Compétences");
+    }
+
+    #[test]
+    fn fails_on_missing_keys() {
+        assert_eq!(
+            t(json!({ "key": "contact.address" }))
+                .unwrap_err()
+                .to_string(),
+            "No translation found for `contact.address`"
+        );
+        assert_eq!(
+            t(json!({ "key": "title.short" })).unwrap_err().to_string(),
+            "No translation found for `title.short`"
+        );
+    }
+
+    #[test]
+    fn fails_without_a_key_argument() {
+        for args in [json!({}), json!({ "name": "title" }), json!({ "key": 1 })] {
+            assert_eq!(
+                t(args).unwrap_err().to_string(),
+                "The `t` function expects a string `key` argument"
+            );
+        }
+    }
+}
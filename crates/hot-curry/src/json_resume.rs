@@ -0,0 +1,231 @@
+use std::collections::BTreeMap;
+
+use serde_json::{Map, Value};
+
+use crate::{
+    errors::{Error, Result},
+    schema::{parse_path, Segment},
+    validation::{self, Problem},
+};
+
+const SCHEMA_URL: &str =
+    "https://raw.githubusercontent.com/jsonresume/resume-schema/v1.0.0/schema.json";
+
+/// A subset of the [JSON Resume schema](https://jsonresume.org/schema/): the known sections and their fields,
+/// with their types, and the date and email formats
+const SCHEMA: &str = include_str!("json_resume.schema.json");
+
+/// Checks the data against the JSON Resume schema
+pub fn validate(data: &Value) -> Result<Vec<Problem>> {
+    let schema = serde_json::from_str::<Value>(SCHEMA)?;
+
+    Ok(validation::validate(&schema, data))
+}
+
+/// Collects the values found at the data path, with the indexes of the arrays crossed to reach them
+fn collect(
+    value: &Value,
+    segments: &[Segment],
+    indexes: &mut Vec<usize>,
+    found: &mut Vec<(Vec<usize>, Value)>,
+) {
+    match segments.split_first() {
+        None if value.is_null() => {}
+        None => found.push((indexes.clone(), value.clone())),
+        Some((Segment::Key(key), segments)) => {
+            if let Some(value) = value.get(key) {
+                collect(value, segments, indexes, found);
+            }
+        }
+        Some((Segment::Items, segments)) => {
+            for (index, item) in value.as_array().into_iter().flatten().enumerate() {
+                indexes.push(index);
+                collect(item, segments, indexes, found);
+                indexes.pop();
+            }
+        }
+    }
+}
+
+/// Writes the value at the resume path, the arrays are indexed with the indexes of the data path,
+/// so `jobs[1].company` mapped to `work[].name` is written to `work[1].name`
+fn assign(target: &mut Value, segments: &[Segment], indexes: &[usize], value: Value) {
+    match segments.split_first() {
+        None => *target = value,
+        Some((Segment::Key(key), segments)) => {
+            if !target.is_object() {
+                *target = Value::Object(Map::new());
+            }
+
+            if let Value::Object(properties) = target {
+                let property = properties.entry(key.clone()).or_insert(Value::Null);
+
+                assign(property, segments, indexes, value);
+            }
+        }
+        Some((Segment::Items, segments)) => {
+            if !target.is_array() {
+                *target = Value::Array(Vec::new());
+            }
+
+            if let Value::Array(items) = target {
+                // Without a matching array in the data path, the value is appended
+                let (index, indexes) = indexes
+                    .split_first()
+                    .map_or((items.len(), indexes), |(index, indexes)| (*index, indexes));
+
+                if items.len() <= index {
+                    items.resize(index + 1, Value::Null);
+                }
+
+                assign(&mut items[index], segments, indexes, value);
+            }
+        }
+    }
+}
+
+/// Removes the array items no value was written to, like `work[0]` when only `jobs[1]` has a mapped value
+fn remove_holes(value: &mut Value) {
+    match value {
+        Value::Array(items) => {
+            items.retain(|item| !item.is_null());
+            items.iter_mut().for_each(remove_holes);
+        }
+        Value::Object(properties) => properties.values_mut().for_each(remove_holes),
+        _ => {}
+    }
+}
+
+/// Maps the data to a JSON Resume, following the mapping table (data paths to resume paths),
+/// the resume is validated against the JSON Resume schema
+pub fn export(data: &Value, mapping: &BTreeMap<String, String>) -> Result<Value> {
+    let mut resume = Value::Object(Map::from_iter([(
+        "$schema".to_string(),
+        Value::from(SCHEMA_URL),
+    )]));
+
+    for (data_path, resume_path) in mapping {
+        let mut found = Vec::new();
+
+        collect(data, &parse_path(data_path), &mut Vec::new(), &mut found);
+
+        let resume_path = parse_path(resume_path);
+
+        for (indexes, value) in found {
+            assign(&mut resume, &resume_path, &indexes, value);
+        }
+    }
+
+    remove_holes(&mut resume);
+
+    let problems = validate(&resume)?;
+
+    if !problems.is_empty() {
+        return Err(Error::JsonResume(problems));
+    }
+
+    Ok(resume)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn mapping(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(data_path, resume_path)| (String::from(*data_path), String::from(*resume_path)))
+            .collect()
+    }
+
+    fn data() -> Value {
+        json!({
+            "name": "Jane",
+            "email": "jane@example.com",
+            "jobs": [
+                { "title": "Dev", "company": "A", "tags": ["rust", "tera"] },
+                { "title": "Lead", "tags": [] },
+                { "company": "C", "tags": ["yaml"] },
+            ],
+        })
+    }
+
+    #[test]
+    fn maps_the_items_by_index() {
+        let resume = export(
+            &data(),
+            &mapping(&[
+                ("name", "basics.name"),
+                ("email", "basics.email"),
+                ("jobs[].company", "work[].name"),
+                ("jobs[].title", "work[].position"),
+                ("jobs[].tags[]", "work[].highlights[]"),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            resume["basics"],
+            json!({ "name": "Jane", "email": "jane@example.com" })
+        );
+        assert_eq!(
+            resume["work"],
+            json!([
+                { "name": "A", "position": "Dev", "highlights": ["rust", "tera"] },
+                { "position": "Lead" },
+                { "name": "C", "highlights": ["yaml"] },
+            ])
+        );
+        assert_eq!(resume["$schema"], SCHEMA_URL);
+    }
+
+    #[test]
+    fn removes_the_items_without_values() {
+        let resume = export(&data(), &mapping(&[("jobs[].company", "work[].name")])).unwrap();
+
+        assert_eq!(resume["work"], json!([{ "name": "A" }, { "name": "C" }]));
+    }
+
+    #[test]
+    fn appends_the_values_without_a_matching_array() {
+        let resume = export(
+            &json!({ "github": "jane", "gitlab": "jane2" }),
+            &mapping(&[
+                ("github", "basics.profiles[].username"),
+                ("gitlab", "basics.profiles[].username"),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            resume["basics"]["profiles"],
+            json!([{ "username": "jane" }, { "username": "jane2" }])
+        );
+    }
+
+    #[test]
+    fn rejects_resumes_not_following_the_schema() {
+        let result = export(
+            &json!({ "email": "jane", "nick": "jj", "jobs": [{ "start": 2020 }] }),
+            &mapping(&[
+                ("email", "basics.email"),
+                ("jobs[].start", "work[].startDate"),
+                ("nick", "hobbies"),
+            ]),
+        );
+
+        let Err(Error::JsonResume(problems)) = result else {
+            panic!("expected JSON Resume problems, got {result:?}");
+        };
+
+        assert_eq!(
+            problems
+                .iter()
+                .map(|problem| problem.path.as_str())
+                .collect::<Vec<_>>(),
+            ["hobbies", "basics.email", "work[0].startDate"]
+        );
+    }
+}
@@ -3,28 +3,47 @@
 #![allow(clippy::missing_errors_doc)]
 
 use std::{
+    collections::HashMap,
     fs,
     path::{Path, PathBuf},
 };
 
+use diagnostics::display_path;
 use filters::markdown;
+use functions::Translate;
+use merge::{Conflict, Merger, Origins};
+use pdf::{PageTemplate, Printer};
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 use tera::{Context, Template, Tera};
-use tera_visitor::VisitorMut;
-use types::{Config, TemplateSource};
+use types::{Config, Document, Language, OutputType, SourceType, TemplateSource, Value, Variant};
 
 pub use crate::errors::{Error, Result};
 use crate::visitor::TeraVariableVisitor;
 
+pub mod analysis;
+pub mod diagnostics;
 pub mod errors;
 pub mod filters;
+pub mod front_matter;
+pub mod functions;
+pub mod json_resume;
+pub mod lint;
+pub mod locate;
+pub mod merge;
+pub mod pdf;
+pub mod scaffold;
+pub mod schema;
 pub mod types;
+pub mod unused;
+pub mod validation;
 pub mod visitor;
 
 pub struct FullTemplate {
     tera: Tera,
     template: Template,
     context: Context,
+    source_conflicts: Vec<Conflict>,
+    source_origins: Origins,
     config: Config,
     template_content: String,
     config_file_base_path: PathBuf,
@@ -35,14 +54,19 @@ impl FullTemplate {
         tera: Tera,
         template: Template,
         context: Context,
+        merger: Merger,
         config: Config,
         template_content: impl Into<String>,
         config_file_base_path: impl Into<PathBuf>,
     ) -> Self {
+        let (source_conflicts, source_origins) = merger.into_parts();
+
         Self {
             tera,
             template,
             context,
+            source_conflicts,
+            source_origins,
             config,
             template_content: template_content.into(),
             config_file_base_path: config_file_base_path.into(),
@@ -56,17 +80,26 @@ impl FullTemplate {
 
         let config_content = fs::read_to_string(config_path)?;
 
-        let config = toml::from_str::<Config>(&config_content)?;
+        let config = toml::from_str::<Config>(&config_content)
+            .map_err(|err| Error::from(err).with_path(display_path(config_path)))?;
+
+        let mut merger = Merger::new(config.source.arrays.clone());
 
-        let source_content =
-            fs::read_to_string(config_file_base_path.join(&config.source.file.path))?;
+        let mut data = serde_json::Value::Object(serde_json::Map::new());
 
-        let context = config
-            .source
-            .file
-            .type_
-            .to_value(source_content)?
-            .into_tera_context()?;
+        for file in &config.source.files {
+            merger.merge(&mut data, file.read(config_file_base_path)?, &file.path);
+        }
+
+        for (key, directory) in &config.source.directories {
+            merger.merge(
+                &mut data,
+                serde_json::json!({ key: directory.read(config_file_base_path)? }),
+                directory.path.to_string_lossy(),
+            );
+        }
+
+        let context = Value::from(data).into_tera_context()?;
 
         let template_content = match &config.template.source {
             TemplateSource::Simple(path) | TemplateSource::Path { path } => {
@@ -75,9 +108,24 @@ impl FullTemplate {
             TemplateSource::Url { url } => reqwest::get(url.as_str()).await?.text().await?,
         };
 
-        let template = Template::new("", None, &template_content)?;
+        let template = Template::new("", None, &template_content).map_err(|err| {
+            let template_path = match &config.template.source {
+                TemplateSource::Simple(path) | TemplateSource::Path { path } => {
+                    display_path(&config_file_base_path.join(path))
+                }
+                TemplateSource::Url { url } => url.to_string(),
+            };
+
+            Error::from(err).with_path(template_path)
+        })?;
 
-        let mut tera = Tera::new(&config_file_base_path.join("*").to_string_lossy())?;
+        let templates_glob = config_file_base_path.join("*");
+
+        // Tera doesn't find any template with a glob starting with `./`, like for the default config path
+        let templates_glob = templates_glob.strip_prefix(".").unwrap_or(&templates_glob);
+
+        let mut tera = Tera::new(&templates_glob.to_string_lossy())
+            .map_err(|err| Error::from(err).with_template_path(config_file_base_path))?;
 
         tera.autoescape_on(Vec::new());
 
@@ -87,39 +135,331 @@ impl FullTemplate {
             tera,
             template,
             context,
+            merger,
             config,
             template_content,
             config_file_base_path,
         ))
     }
 
+    /// Generates the files of all the output types, once per variant and per language if some are configured,
+    /// like `cv.backend.en.pdf`
     pub fn generate_files(&mut self) -> Result<()> {
-        let output = self
-            .tera
-            .render_str(&self.template_content, &self.context)?;
-
         let output_base_path = self.config_file_base_path().join(&self.config.output.path);
 
         let output_base_path = output_base_path.to_string_lossy();
 
-        self.config
-            .output
-            .types
+        let variants = if self.config.variants.is_empty() {
+            vec![None]
+        } else {
+            self.config.variants.iter().map(Some).collect()
+        };
+
+        let languages = if self.config.i18n.is_empty() {
+            vec![None]
+        } else {
+            self.config.i18n.iter().map(Some).collect()
+        };
+
+        let renders = variants
+            .iter()
+            .flat_map(|variant| languages.iter().map(move |language| (variant, language)))
+            .collect::<Vec<_>>();
+
+        let outputs = renders
             .par_iter()
-            .try_for_each(|type_| type_.write(&output_base_path, &output))?;
+            .map(|(variant, language)| {
+                let mut output_path = output_base_path.to_string();
+
+                if let Some((name, _)) = variant {
+                    output_path.push_str(&format!(".{name}"));
+                }
+
+                if let Some((code, _)) = language {
+                    output_path.push_str(&format!(".{code}"));
+                }
+
+                let output = self.render(
+                    variant.map(|(_, variant)| variant),
+                    language.map(|(_, language)| language),
+                )?;
+
+                Ok((output_path, output))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Shared by all the pdf outputs, the browser is started with the first one
+        let printer = Printer::new(&self.config.output.pdf, &self.config_file_base_path);
+
+        outputs
+            .par_iter()
+            .flat_map(|(output_path, output)| {
+                self.config
+                    .output
+                    .types
+                    .par_iter()
+                    .map(move |type_| (type_, output_path, output))
+            })
+            .try_for_each(|(type_, output_path, output)| {
+                type_.write(output_path, output, &printer)
+            })?;
 
         Ok(())
     }
 
+    /// Renders the template, and the pdf header and footer, with the overlay of the language merged over the data,
+    /// and its translations, then the data is filtered by the variant
+    fn render(&self, variant: Option<&Variant>, language: Option<&Language>) -> Result<Document> {
+        let mut data = self.context.clone().into_json();
+
+        // Each render has its own `Tera` instance, so each language has its own `t` function
+        let mut tera = self.tera.clone();
+
+        if let Some(language) = language {
+            if let Some(overlay) = &language.overlay {
+                Merger::new(self.config.source.arrays.clone()).merge(
+                    &mut data,
+                    overlay.read(&self.config_file_base_path)?,
+                    &overlay.path,
+                );
+            }
+
+            let translations = language
+                .translations
+                .as_ref()
+                .map(|translations| translations.read(&self.config_file_base_path))
+                .transpose()?
+                .unwrap_or_default();
+
+            tera.register_function("t", Translate::new(translations));
+        }
+
+        if let Some(variant) = variant {
+            variant.filter(&mut data);
+        }
+
+        let context = Context::from_value(data)?;
+
+        let pdf = &self.config.output.pdf;
+
+        let prints_pdf = self
+            .config
+            .output
+            .types
+            .iter()
+            .any(|type_| matches!(type_, OutputType::Pdf));
+
+        let mut render_page_template = |page_template: Option<&PageTemplate>| {
+            page_template
+                .filter(|_| prints_pdf)
+                .map(|page_template| -> Result<String> {
+                    let page_template = page_template.read(&self.config_file_base_path)?;
+
+                    Ok(tera.render_str(&page_template, &context)?)
+                })
+                .transpose()
+        };
+
+        let header = render_page_template(pdf.header.as_ref())?;
+        let footer = render_page_template(pdf.footer.as_ref())?;
+
+        Ok(Document {
+            html: tera.render_str(&self.template_content, &context)?,
+            header,
+            footer,
+        })
+    }
+
     #[must_use]
-    pub fn visit_renderer(&self) -> TeraVariableVisitor {
-        let mut visitor = TeraVariableVisitor::new();
+    pub fn visit_renderer(&self) -> TeraVariableVisitor<'_> {
+        let mut visitor = TeraVariableVisitor::with_tera(&self.tera);
 
-        visitor.visit_ast_mut(&self.template.ast);
+        visitor.visit_template(&self.template);
 
         visitor
     }
 
+    /// The JSON Schema the data file must follow, inferred from the template
+    #[must_use]
+    pub fn json_schema(&self) -> serde_json::Value {
+        schema::infer_json_schema(&self.visit_renderer())
+    }
+
+    /// Checks the data file against the template, and returns all the problems found,
+    /// with the file and the position of their value when known
+    #[must_use]
+    pub fn validate(&self) -> Vec<validation::Problem> {
+        let mut problems =
+            validation::validate(&self.json_schema(), &self.context.clone().into_json());
+
+        let source = &self.config.source;
+        let mut file_contents = HashMap::new();
+
+        for problem in &mut problems {
+            // A missing value has no origin, unless one of its ancestors has
+            let origin = self.source_origins.find(&problem.path).or_else(|| {
+                match (&source.files[..], source.directories.is_empty()) {
+                    ([file], true) => Some(merge::Origin {
+                        file: file.path.clone(),
+                        path: problem.path.clone(),
+                    }),
+                    _ => None,
+                }
+            });
+
+            let Some(origin) = origin else {
+                continue;
+            };
+
+            let path = self.config_file_base_path.join(&origin.file);
+
+            // The values of the directories come from several files, they are not located
+            problem.position = source
+                .files
+                .iter()
+                .find(|file| file.path == origin.file)
+                .and_then(|file| {
+                    let content = file_contents
+                        .entry(origin.file.clone())
+                        .or_insert_with(|| fs::read_to_string(&path).ok())
+                        .as_deref()?;
+
+                    locate::locate(content, &file.type_, &origin.path)
+                });
+            problem.file = Some(display_path(&path));
+        }
+
+        problems
+    }
+
+    /// The paths of the data file that the template never reads
+    #[must_use]
+    pub fn unused_data_paths(&self) -> Vec<String> {
+        unused::find_unused_paths(&self.visit_renderer(), &self.context.clone().into_json())
+    }
+
+    /// The full analysis of the template, and of the data it doesn't use
+    #[must_use]
+    pub fn analysis(&self) -> analysis::Analysis {
+        analysis::Analysis::new(&self.visit_renderer(), self.unused_data_paths())
+    }
+
+    /// A placeholder data file in the given format, following the shape the template expects
+    pub fn scaffold(&self, source_type: &SourceType) -> Result<String> {
+        scaffold::scaffold(&self.json_schema(), source_type)
+    }
+
+    /// Maps the data to a JSON Resume, following the `[export.json-resume]` mapping table, and writes it,
+    /// returns the path of the written file
+    pub fn export_json_resume(&self) -> Result<PathBuf> {
+        let export = &self.config.export.json_resume;
+
+        let resume = json_resume::export(&self.context.clone().into_json(), &export.mapping)?;
+
+        let path = self
+            .config_file_base_path()
+            .join(export.path.as_deref().unwrap_or(Path::new("resume.json")));
+
+        fs::write(&path, serde_json::to_string_pretty(&resume)?)?;
+
+        Ok(path)
+    }
+
+    /// Checks the template, and all the templates it uses, against the lint rules configured in the `[lint]` section
+    #[must_use]
+    pub fn lint(&self) -> Vec<lint::Finding> {
+        let visitor = self.visit_renderer();
+
+        let optional_idents = visitor.optional_idents();
+
+        let data = self.context.clone().into_json();
+
+        let mut findings = lint::Linter::new(
+            &self.tera,
+            &self.template,
+            self.template_name(),
+            Some(&self.template_content),
+            &self.config.lint,
+            &optional_idents,
+            &data,
+        )
+        .lint();
+
+        let mut template_names = visitor.visited_templates().iter().collect::<Vec<_>>();
+
+        template_names.sort();
+
+        for template_name in template_names {
+            if let Some(template) = self.tera.templates.get(template_name) {
+                let source = self.template_source(template_name);
+
+                findings.extend(
+                    lint::Linter::new(
+                        &self.tera,
+                        template,
+                        template_name,
+                        source.as_deref(),
+                        &self.config.lint,
+                        &optional_idents,
+                        &data,
+                    )
+                    .lint(),
+                );
+            }
+        }
+
+        findings
+    }
+
+    /// The template printed back from its AST, which normalises the tags and expressions
+    #[must_use]
+    pub fn formatted_template(&self) -> String {
+        tera_visitor::printer::print_with_source(&self.template_content, &self.template.ast)
+    }
+
+    /// The formatted template, or an error if it would lose something from the source,
+    /// like comments or the whitespace removed by the trim markers
+    pub fn lossless_formatted_template(&self) -> Result<String> {
+        Ok(tera_visitor::printer::print_lossless(
+            &self.template_content,
+            &self.template.ast,
+        )?)
+    }
+
+    /// The local path of the template, `None` if the template is fetched from a url
+    #[must_use]
+    pub fn template_path(&self) -> Option<PathBuf> {
+        match &self.config.template.source {
+            TemplateSource::Simple(path) | TemplateSource::Path { path } => {
+                Some(self.config_file_base_path.join(path))
+            }
+            TemplateSource::Url { .. } => None,
+        }
+    }
+
+    /// The template path or url, as written in the config file
+    #[must_use]
+    pub fn template_name(&self) -> String {
+        match &self.config.template.source {
+            TemplateSource::Simple(path) | TemplateSource::Path { path } => {
+                path.to_string_lossy().into_owned()
+            }
+            TemplateSource::Url { url } => url.to_string(),
+        }
+    }
+
+    /// The source of the main template, or of a template loaded in the `Tera` instance
+    #[must_use]
+    pub fn template_source(&self, template_name: &str) -> Option<String> {
+        if template_name == self.template_name() {
+            return Some(self.template_content.clone());
+        }
+
+        let path = self.tera.templates.get(template_name)?.path.as_ref()?;
+
+        fs::read_to_string(path).ok()
+    }
+
     #[must_use]
     pub fn tera(&self) -> &Tera {
         &self.tera
@@ -135,6 +475,12 @@ impl FullTemplate {
         &self.context
     }
 
+    /// The values of the source files overridden by a following file
+    #[must_use]
+    pub fn source_conflicts(&self) -> &[Conflict] {
+        &self.source_conflicts
+    }
+
     #[must_use]
     pub fn config(&self) -> &Config {
         &self.config
@@ -150,3 +496,95 @@ impl FullTemplate {
         &self.config_file_base_path
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn full_template(files: &[(&str, &str)]) -> (tempfile::TempDir, FullTemplate) {
+        let directory = tempfile::tempdir().unwrap();
+
+        for (name, content) in files {
+            fs::write(directory.path().join(name), content).unwrap();
+        }
+
+        let full_template = FullTemplate::from_config_path(directory.path().join("hot_curry.toml"))
+            .await
+            .unwrap();
+
+        (directory, full_template)
+    }
+
+    const CONFIG: (&str, &str) = (
+        "hot_curry.toml",
+        r#"
+            [source]
+            path = "data.yml"
+
+            [template]
+            source = "cv.html"
+
+            [output]
+            path = "cv"
+            types = ["html"]
+
+            [i18n.fr]
+            overlay = "data.fr.yml"
+            translations = "fr.toml"
+
+            [variants.backend]
+            include = ["backend"]
+        "#,
+    );
+
+    const DATA: (&str, &str) = (
+        "data.yml",
+        "jobs:\n  - { title: Backend, tags: [backend] }\n  - { title: Frontend, tags: [frontend] }\n",
+    );
+
+    #[tokio::test]
+    async fn merges_the_language_overlay_before_filtering_the_variant() {
+        let (_directory, full_template) = full_template(&[
+            CONFIG,
+            DATA,
+            ("cv.html", "{% for job in jobs %}{{ job.title }};{% endfor %}"),
+            // The tags of the overlay replace the ones of the data
+            (
+                "data.fr.yml",
+                "jobs:\n  - { title: Dorsal, tags: [frontend] }\n  - { title: Frontal, tags: [backend] }\n",
+            ),
+            ("fr.toml", ""),
+        ])
+        .await;
+
+        let config = full_template.config();
+        let (variant, language) = (&config.variants["backend"], &config.i18n["fr"]);
+
+        let render = |variant, language| full_template.render(variant, language).unwrap().html;
+
+        assert_eq!(render(None, None), "Backend;Frontend;");
+        assert_eq!(render(Some(variant), None), "Backend;");
+        assert_eq!(render(None, Some(language)), "Dorsal;Frontal;");
+        assert_eq!(render(Some(variant), Some(language)), "Frontal;");
+    }
+
+    #[tokio::test]
+    async fn registers_the_translate_function_with_a_language() {
+        let (_directory, full_template) = full_template(&[
+            CONFIG,
+            DATA,
+            ("cv.html", "{{ t(key='jobs.title') }}"),
+            ("data.fr.yml", "{}"),
+            ("fr.toml", "[jobs]\ntitle = \"Expériences\"\n"),
+        ])
+        .await;
+
+        let language = &full_template.config().i18n["fr"];
+
+        assert_eq!(
+            full_template.render(None, Some(language)).unwrap().html,
+            "Expériences"
+        );
+        assert!(full_template.render(None, None).is_err());
+    }
+}
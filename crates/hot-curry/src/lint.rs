@@ -0,0 +1,589 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display, Formatter},
+};
+
+use serde::{de, Deserialize, Deserializer};
+use serde_json::Value;
+use tera::{
+    ast::{Expr, ExprVal, FilterSection, Forloop, If, MacroCall, Set, WS},
+    Template, Tera,
+};
+use tera_visitor::{Ancestor, Ancestry, VisitorMut};
+
+use crate::{
+    diagnostics::{self, Position},
+    schema::{parse_path, Segment},
+    types::LintConfig,
+    visitor::Scopes,
+};
+
+/// Filters handled by the Tera renderer itself, they're not registered in the `Tera` instance
+const RENDERER_FILTERS: &[&str] = &["default", "safe"];
+
+/// Filters that make raw HTML safe to render, or mark it as intentionally rendered as is
+const HTML_FILTERS: &[&str] = &["escape", "markdown", "safe", "striptags"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+    /// A macro called from a namespace that's not imported, or that doesn't define it
+    UndefinedMacro,
+    /// A filter that's neither built into Tera nor registered by hot-curry
+    UnknownFilter,
+    /// A `for` loop over potentially optional data, without an `{% else %}` body
+    EmptyLoopOverOptional,
+    /// Data containing HTML rendered without going through `markdown`
+    RawHtmlWithoutMarkdown,
+    /// An `elif` branch that can't be reached, as a previous branch has the same condition or is always true
+    UnreachableElif,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 5] = [
+        Rule::UndefinedMacro,
+        Rule::UnknownFilter,
+        Rule::EmptyLoopOverOptional,
+        Rule::RawHtmlWithoutMarkdown,
+        Rule::UnreachableElif,
+    ];
+
+    /// The ids of all the rules, in the order of `ALL`
+    const IDS: [&'static str; Self::ALL.len()] = {
+        let mut ids = [""; Self::ALL.len()];
+        let mut index = 0;
+
+        while index < ids.len() {
+            ids[index] = Self::ALL[index].id();
+            index += 1;
+        }
+
+        ids
+    };
+
+    #[must_use]
+    pub const fn id(self) -> &'static str {
+        match self {
+            Rule::UndefinedMacro => "undefined-macro",
+            Rule::UnknownFilter => "unknown-filter",
+            Rule::EmptyLoopOverOptional => "empty-loop-over-optional",
+            Rule::RawHtmlWithoutMarkdown => "raw-html-without-markdown",
+            Rule::UnreachableElif => "unreachable-elif",
+        }
+    }
+
+    #[must_use]
+    pub fn default_severity(self) -> Severity {
+        match self {
+            Rule::UndefinedMacro | Rule::UnknownFilter => Severity::Error,
+            Rule::EmptyLoopOverOptional | Rule::RawHtmlWithoutMarkdown | Rule::UnreachableElif => {
+                Severity::Warning
+            }
+        }
+    }
+}
+
+// The toml deserializer only gives strings for map keys, which the derived implementation rejects
+impl<'de> Deserialize<'de> for Rule {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let id = String::deserialize(deserializer)?;
+
+        Rule::ALL
+            .into_iter()
+            .find(|rule| rule.id() == id)
+            .ok_or_else(|| de::Error::unknown_variant(&id, &Rule::IDS))
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.id())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The rule is disabled
+    Off,
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Off => write!(f, "off"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Where a finding is: the template, the position in the template if found, and the nodes containing it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub template: String,
+    pub position: Option<Position>,
+    pub ancestors: Vec<Ancestor>,
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.template)?;
+
+        if let Some(position) = self.position {
+            write!(f, ":{position}")?;
+        }
+
+        if !self.ancestors.is_empty() {
+            let ancestors = self
+                .ancestors
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+
+            write!(f, " ({})", ancestors.join(" > "))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub rule: Rule,
+    pub severity: Severity,
+    pub location: Location,
+    pub message: String,
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}[{}]: {}",
+            self.location, self.severity, self.rule, self.message
+        )
+    }
+}
+
+/// All the values found at a data path, the `[]` segments going through all the items
+fn values_at<'v>(value: &'v Value, segments: &[Segment]) -> Vec<&'v Value> {
+    let Some((segment, segments)) = segments.split_first() else {
+        return vec![value];
+    };
+
+    match (segment, value) {
+        (Segment::Key(key), Value::Object(object)) => object
+            .get(key)
+            .map(|value| values_at(value, segments))
+            .unwrap_or_default(),
+        (Segment::Items, Value::Array(items)) => items
+            .iter()
+            .flat_map(|item| values_at(item, segments))
+            .collect(),
+        (Segment::Items, Value::Object(object)) => object
+            .values()
+            .flat_map(|item| values_at(item, segments))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Looks for something like `<p>`, `</p>`, or `<!-- -->`
+fn contains_html(value: &str) -> bool {
+    value.match_indices('<').any(|(index, _)| {
+        value[index + 1..]
+            .chars()
+            .next()
+            .map_or(false, |c| c.is_ascii_alphabetic() || c == '/' || c == '!')
+    })
+}
+
+/// A visitor checking a single template against the lint rules
+#[derive(Debug)]
+pub struct Linter<'a> {
+    tera: &'a Tera,
+    template: &'a Template,
+    template_name: String,
+    /// The template source, to find the position of the findings
+    source: Option<&'a str>,
+    /// How many times each searched piece of source was seen, to find the position of the next one
+    occurrences: HashMap<String, usize>,
+    /// The unreachable branches of the enclosing `if`s
+    unreachable_branches: Vec<Vec<usize>>,
+    config: &'a LintConfig,
+    /// The optional data paths of the whole analysis, as the optional data can be used in other templates
+    optional_idents: &'a HashSet<String>,
+    data: &'a Value,
+    ancestry: Ancestry,
+    scopes: Scopes,
+    findings: Vec<Finding>,
+}
+
+impl<'a> Linter<'a> {
+    #[must_use]
+    pub fn new(
+        tera: &'a Tera,
+        template: &'a Template,
+        template_name: impl Into<String>,
+        source: Option<&'a str>,
+        config: &'a LintConfig,
+        optional_idents: &'a HashSet<String>,
+        data: &'a Value,
+    ) -> Self {
+        Self {
+            tera,
+            template,
+            template_name: template_name.into(),
+            source,
+            occurrences: HashMap::new(),
+            unreachable_branches: Vec::new(),
+            config,
+            optional_idents,
+            data,
+            ancestry: Ancestry::new(),
+            scopes: Scopes::default(),
+            findings: Vec::new(),
+        }
+    }
+
+    /// Checks the whole template and returns the findings, in the template order
+    #[must_use]
+    pub fn lint(mut self) -> Vec<Finding> {
+        let template = self.template;
+
+        self.visit_ast_mut(&template.ast);
+
+        self.findings
+    }
+
+    /// Finds the position of the next occurrence of `needle`, in the order the nodes are visited
+    fn locate(&mut self, needle: &str) -> Option<Position> {
+        let source = self.source?;
+
+        let occurrence = self.occurrences.entry(needle.to_string()).or_default();
+
+        let position = diagnostics::find(source, needle, *occurrence);
+
+        *occurrence += 1;
+
+        position
+    }
+
+    fn report(&mut self, rule: Rule, position: Option<Position>, message: impl Into<String>) {
+        let severity = self.config.severity(rule);
+
+        if severity == Severity::Off {
+            return;
+        }
+
+        self.findings.push(Finding {
+            rule,
+            severity,
+            location: Location {
+                template: self.template_name.clone(),
+                position,
+                ancestors: self.ancestry.ancestors().to_vec(),
+            },
+            message: message.into(),
+        });
+    }
+
+    /// Maps an ident to the data path it reads, as far as the loops and the variables of this template are concerned
+    fn resolve_ident(&self, ident: &str) -> Option<String> {
+        self.scopes.resolve_ident(&self.ancestry, ident)
+    }
+
+    fn is_macro_defined(&self, macro_call: &MacroCall) -> bool {
+        if macro_call.namespace == "self" {
+            return self.template.macros.contains_key(&macro_call.name);
+        }
+
+        self.template
+            .imported_macro_files
+            .iter()
+            .filter(|(_, namespace)| *namespace == macro_call.namespace)
+            .filter_map(|(path, _)| self.tera.templates.get(path))
+            .any(|template| template.macros.contains_key(&macro_call.name))
+    }
+
+    fn check_filter(&mut self, name: &str, needle: &str) {
+        let position = self.locate(needle);
+
+        if !self.tera.filters.contains_key(name) && !RENDERER_FILTERS.contains(&name) {
+            self.report(
+                Rule::UnknownFilter,
+                position,
+                format!("unknown filter `{name}`"),
+            );
+        }
+    }
+}
+
+impl VisitorMut for Linter<'_> {
+    fn ancestry_mut(&mut self) -> Option<&mut Ancestry> {
+        Some(&mut self.ancestry)
+    }
+
+    fn visit_variable_block_mut(&mut self, ws: &WS, expr: &Expr) {
+        let is_html_safe = expr
+            .filters
+            .iter()
+            .any(|filter| HTML_FILTERS.contains(&filter.name.as_str()));
+
+        if let ExprVal::Ident(ident) = &expr.val {
+            let position = self.locate(&format!("{{{{ {ident}"));
+
+            if let (Some(path), false) = (self.resolve_ident(ident), is_html_safe) {
+                let has_html = values_at(self.data, &parse_path(&path))
+                    .into_iter()
+                    .any(|value| value.as_str().map_or(false, contains_html));
+
+                if has_html {
+                    self.report(
+                        Rule::RawHtmlWithoutMarkdown,
+                        position,
+                        format!("`{path}` contains HTML but is rendered without `markdown`"),
+                    );
+                }
+            }
+        }
+
+        tera_visitor::visit_variable_block_mut(self, ws, expr);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &Expr) {
+        for filter in &expr.filters {
+            self.check_filter(&filter.name, &format!("| {}", filter.name));
+        }
+
+        tera_visitor::visit_expr_mut(self, expr);
+    }
+
+    fn visit_filter_section_mut(
+        &mut self,
+        left_ws: &WS,
+        filter_section: &FilterSection,
+        right_ws: &WS,
+    ) {
+        self.check_filter(
+            &filter_section.filter.name,
+            &format!("filter {}", filter_section.filter.name),
+        );
+
+        tera_visitor::visit_filter_section_mut(self, left_ws, filter_section, right_ws);
+    }
+
+    fn visit_macro_call_mut(&mut self, macro_call: &MacroCall) {
+        let position = self.locate(&format!("{}::{}", macro_call.namespace, macro_call.name));
+
+        if !self.is_macro_defined(macro_call) {
+            self.report(
+                Rule::UndefinedMacro,
+                position,
+                format!(
+                    "the macro `{}::{}` is not defined",
+                    macro_call.namespace, macro_call.name
+                ),
+            );
+        }
+
+        tera_visitor::visit_macro_call_mut(self, macro_call);
+    }
+
+    fn visit_forloop_mut(&mut self, left_ws: &WS, forloop: &Forloop, right_ws: &WS) {
+        let container_path = match &forloop.container.val {
+            ExprVal::Ident(ident) => self.resolve_ident(ident),
+            _ => None,
+        };
+
+        let position = self.locate(&match &forloop.key {
+            Some(key) => format!("{{% for {key}, {} in", forloop.value),
+            None => format!("{{% for {} in", forloop.value),
+        });
+
+        // A filter like `default` can handle the missing data
+        if let (Some(path), None, true) = (
+            &container_path,
+            &forloop.empty_body,
+            forloop.container.filters.is_empty(),
+        ) {
+            if self.optional_idents.contains(path) {
+                self.report(
+                    Rule::EmptyLoopOverOptional,
+                    position,
+                    format!("`{path}` is potentially optional, but the loop has no `else` body"),
+                );
+            }
+        }
+
+        self.scopes.push_forloop(forloop, container_path.as_deref());
+
+        tera_visitor::visit_forloop_mut(self, left_ws, forloop, right_ws);
+
+        self.scopes.pop_forloop();
+    }
+
+    fn visit_set_mut(&mut self, ws: &WS, set: &Set) {
+        tera_visitor::visit_set_mut(self, ws, set);
+
+        self.scopes.insert_set(&self.ancestry, set);
+    }
+
+    fn visit_if_mut(&mut self, if_: &If, ws: &WS) {
+        let unreachable_branches = (1..if_.conditions.len())
+            .filter(|branch| {
+                let condition = &if_.conditions[*branch].1;
+
+                if_.conditions[..*branch].iter().any(|(_, previous, _)| {
+                    previous == condition
+                        || (!previous.negated
+                            && previous.filters.is_empty()
+                            && previous.val == ExprVal::Bool(true))
+                })
+            })
+            .collect();
+
+        self.unreachable_branches.push(unreachable_branches);
+
+        tera_visitor::visit_if_mut(self, if_, ws);
+
+        self.unreachable_branches.pop();
+    }
+
+    // The `elif` conditions are checked when visited, so their positions are searched in the template order
+    fn visit_else_if_expr_mut(&mut self, expr: &Expr) {
+        let position = self.locate("{% elif");
+
+        let is_unreachable = match (self.ancestry.parent(), self.unreachable_branches.last()) {
+            (Some(Ancestor::If { branch }), Some(unreachable_branches)) => {
+                unreachable_branches.contains(branch)
+            }
+            _ => false,
+        };
+
+        if is_unreachable {
+            self.report(
+                Rule::UnreachableElif,
+                position,
+                "a previous branch has the same condition or is always true",
+            );
+        }
+
+        tera_visitor::visit_else_if_expr_mut(self, expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn lint(source: &str, data: &Value, optional_idents: &[&str]) -> Vec<(Rule, Option<Position>)> {
+        let mut tera = Tera::default();
+
+        tera.add_raw_template("t.html", source).unwrap();
+
+        let template = &tera.templates["t.html"];
+        let config = LintConfig::default();
+        let optional_idents = optional_idents.iter().map(ToString::to_string).collect();
+
+        Linter::new(
+            &tera,
+            template,
+            "t.html",
+            Some(source),
+            &config,
+            &optional_idents,
+            data,
+        )
+        .lint()
+        .into_iter()
+        .map(|finding| (finding.rule, finding.location.position))
+        .collect()
+    }
+
+    #[test]
+    fn reports_raw_html_in_data() {
+        let data = json!({ "bio": "<p>Hi</p>", "jobs": [{ "summary": "<b>A</b>" }] });
+        let source = "{{ bio | safe }}\n{% for job in jobs %}\n{{- job.summary }}{% endfor %}";
+
+        assert_eq!(
+            lint(source, &data, &[]),
+            vec![(Rule::RawHtmlWithoutMarkdown, Some(Position::new(3, 1)))]
+        );
+    }
+
+    #[test]
+    fn ignores_the_template_variables() {
+        let data = json!({ "bio": "<p>Hi</p>", "skills": { "<b>": 1 } });
+        let source = "{% set bio = \"plain\" %}{{ bio }}\
+            {% for key, value in skills %}{{ key }}{% set summary = value %}{{ summary }}{% endfor %}";
+
+        assert_eq!(lint(source, &data, &[]), vec![]);
+    }
+
+    #[test]
+    fn reports_unknown_filters_and_macros() {
+        let source =
+            "{# {{ a | nope }} #}{{ a | nope }}\n{% filter nope %}x{% endfilter %}{{ m::m() }}";
+
+        assert_eq!(
+            lint(source, &json!({}), &[]),
+            vec![
+                (Rule::UnknownFilter, Some(Position::new(1, 26))),
+                (Rule::UnknownFilter, Some(Position::new(2, 4))),
+                (Rule::UndefinedMacro, Some(Position::new(2, 37))),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_loops_over_optional_data_without_else() {
+        let source = "{% for job in jobs %}{% endfor %}\
+            {% for job in jobs %}{% else %}none{% endfor %}\
+            {% for job in jobs | default(value=[]) %}{% endfor %}";
+
+        assert_eq!(
+            lint(source, &json!({}), &["jobs"]),
+            vec![(Rule::EmptyLoopOverOptional, Some(Position::new(1, 1)))]
+        );
+    }
+
+    #[test]
+    fn reports_unreachable_elifs() {
+        let source =
+            "{% if a %}{% elif b %}{% elif a %}{% endif %}{% if true %}{% elif b %}{% endif %}";
+
+        assert_eq!(
+            lint(source, &json!({}), &[]),
+            vec![
+                (Rule::UnreachableElif, Some(Position::new(1, 23))),
+                (Rule::UnreachableElif, Some(Position::new(1, 59))),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_the_rule_ids() {
+        let config = |toml: &str| toml::from_str::<LintConfig>(toml).map_err(|err| err.to_string());
+
+        for rule in Rule::ALL {
+            let config = config(&format!("rules = {{ {rule} = 'off' }}")).unwrap();
+
+            assert_eq!(config.severity(rule), Severity::Off);
+        }
+
+        let error = config("rules = { unknown-rule = 'off' }").unwrap_err();
+
+        assert!(error.contains(
+            "unknown variant `unknown-rule`, expected one of `undefined-macro`, `unknown-filter`, \
+             `empty-loop-over-optional`, `raw-html-without-markdown`, `unreachable-elif`"
+        ));
+    }
+}
@@ -0,0 +1,386 @@
+use std::collections::HashMap;
+
+use yaml_rust::{
+    parser::{Event, MarkedEventReceiver, Parser},
+    scanner::Marker,
+};
+
+use crate::{
+    diagnostics::Position,
+    front_matter::{self, CONTENT_KEY},
+    schema::{ancestor_paths, key_path},
+    types::SourceType,
+};
+
+/// Finds where the value at a data path, like `jobs[1].title`, is written in a source file,
+/// or where its closest written ancestor is, like `jobs[1]` when `title` is missing
+#[must_use]
+pub fn locate(source: &str, source_type: &SourceType, path: &str) -> Option<Position> {
+    let positions = match source_type {
+        SourceType::Yaml | SourceType::Json | SourceType::JsonResume => yaml_positions(source),
+        SourceType::Toml => toml_positions(source),
+        SourceType::Markdown => markdown_positions(source),
+    };
+
+    ancestor_paths(path)
+        .into_iter()
+        .rev()
+        .find_map(|path| positions.get(path).copied())
+}
+
+#[derive(Debug)]
+enum Frame {
+    /// The key being read, `None` when the next scalar is a key
+    Mapping {
+        path: String,
+        key: Option<String>,
+    },
+    Sequence {
+        path: String,
+        index: usize,
+    },
+}
+
+/// Records the position of each key, and of each array item, json being read as yaml
+#[derive(Debug, Default)]
+struct YamlPositions {
+    stack: Vec<Frame>,
+    /// The array items waiting for their first scalar, since the marks of the collection starts are not reliable
+    pending_items: Vec<String>,
+    positions: HashMap<String, Position>,
+}
+
+impl YamlPositions {
+    /// The path of the next value
+    fn value_path(&mut self) -> String {
+        match self.stack.last_mut() {
+            Some(Frame::Mapping { path, key }) => key
+                .take()
+                .map_or_else(|| path.clone(), |key| key_path(path, &key)),
+            Some(Frame::Sequence { path, index }) => {
+                let item_path = format!("{path}[{index}]");
+
+                *index += 1;
+
+                self.pending_items.push(item_path.clone());
+
+                item_path
+            }
+            None => String::new(),
+        }
+    }
+}
+
+impl MarkedEventReceiver for YamlPositions {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        let is_scalar = matches!(event, Event::Scalar(..) | Event::Alias(_));
+
+        match event {
+            Event::Scalar(value, ..) => {
+                if let Some(Frame::Mapping {
+                    path,
+                    key: key @ None,
+                }) = self.stack.last_mut()
+                {
+                    self.positions.insert(
+                        key_path(path, &value),
+                        Position::new(mark.line(), mark.col() + 1),
+                    );
+                    *key = Some(value);
+                } else {
+                    self.value_path();
+                }
+            }
+            Event::Alias(_) => {
+                self.value_path();
+            }
+            Event::MappingStart(_) => {
+                let path = self.value_path();
+
+                self.stack.push(Frame::Mapping { path, key: None });
+            }
+            Event::SequenceStart(_) => {
+                let path = self.value_path();
+
+                self.stack.push(Frame::Sequence { path, index: 0 });
+            }
+            Event::MappingEnd | Event::SequenceEnd => {
+                self.stack.pop();
+            }
+            _ => {}
+        }
+
+        if is_scalar {
+            let position = Position::new(mark.line(), mark.col() + 1);
+
+            for item in self.pending_items.drain(..) {
+                self.positions.entry(item).or_insert(position);
+            }
+        }
+    }
+}
+
+fn yaml_positions(source: &str) -> HashMap<String, Position> {
+    let mut positions = YamlPositions::default();
+
+    // The positions read before a syntax error are still useful
+    let _ = Parser::new(source.chars()).load(&mut positions, false);
+
+    positions.positions
+}
+
+/// Splits a toml key like `a."b.c".d` on the dots outside of quotes, and removes the quotes
+fn toml_keys(key: &str) -> Vec<String> {
+    let mut keys = vec![String::new()];
+    let mut quote = None;
+
+    for c in key.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '.' => keys.push(String::new()),
+            None if c.is_whitespace() => {}
+            _ => keys.last_mut().unwrap().push(c),
+        }
+    }
+
+    keys
+}
+
+/// The brackets opened and not closed on the line, outside of strings and comments
+fn open_brackets(line: &str) -> isize {
+    let mut depth = 0;
+    let mut quote = None;
+
+    for c in line.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' => break,
+            None if c == '[' || c == '{' => depth += 1,
+            None if c == ']' || c == '}' => depth -= 1,
+            _ => {}
+        }
+    }
+
+    depth
+}
+
+/// Reads the table headers and the `key = value` lines, the values spanning several lines are skipped
+fn toml_positions(source: &str) -> HashMap<String, Position> {
+    let mut positions = HashMap::new();
+    // The number of items of the arrays of tables, by path
+    let mut array_lengths: HashMap<String, usize> = HashMap::new();
+    let mut table = String::new();
+    let mut open_value = 0;
+    let mut multiline_string: Option<&str> = None;
+
+    for (index, line) in source.lines().enumerate() {
+        let trimmed = line.trim_start();
+        let position = Position::new(index + 1, line.len() - trimmed.len() + 1);
+
+        if let Some(delimiter) = multiline_string {
+            if line.contains(delimiter) {
+                multiline_string = None;
+            }
+
+            continue;
+        }
+
+        if open_value > 0 {
+            open_value += open_brackets(line);
+
+            continue;
+        }
+
+        let header = trimmed
+            .strip_prefix("[[")
+            .and_then(|header| header.split_once("]]"))
+            .map(|(header, _)| (header, true))
+            .or_else(|| {
+                trimmed
+                    .strip_prefix('[')
+                    .and_then(|header| header.split_once(']'))
+                    .map(|(header, _)| (header, false))
+            });
+
+        if let Some((header, is_array)) = header {
+            table = String::new();
+
+            let keys = toml_keys(header);
+
+            // The tables under an array of tables belong to its last item
+            for (index, key) in keys.iter().enumerate() {
+                table = key_path(&table, key);
+
+                match array_lengths.get(&table) {
+                    Some(length) if index + 1 < keys.len() => {
+                        table = format!("{table}[{}]", length.saturating_sub(1));
+                    }
+                    _ => {}
+                }
+            }
+
+            if is_array {
+                let length = array_lengths.entry(table.clone()).or_default();
+
+                positions.entry(table.clone()).or_insert(position);
+                table = format!("{table}[{length}]");
+                *length += 1;
+            }
+
+            positions.insert(table.clone(), position);
+
+            continue;
+        }
+
+        if trimmed.starts_with('#') {
+            continue;
+        }
+
+        let Some((key, value)) = trimmed.split_once('=') else {
+            continue;
+        };
+
+        let path = toml_keys(key)
+            .iter()
+            .fold(table.clone(), |path, key| key_path(&path, key));
+
+        positions.insert(path, position);
+
+        let value = value.trim_start();
+
+        multiline_string = ["\"\"\"", "'''"].into_iter().find(|delimiter| {
+            value
+                .strip_prefix(delimiter)
+                .map_or(false, |rest| !rest.contains(delimiter))
+        });
+        open_value = open_brackets(value).max(0);
+    }
+
+    positions
+}
+
+/// The front matter is yaml or toml, and the body is the `content` key
+fn markdown_positions(source: &str) -> HashMap<String, Position> {
+    let (front_matter, body) = front_matter::split(source);
+
+    // The opening delimiter is replaced by an empty line so the positions point to the right line
+    let mut positions = match front_matter {
+        Some(("---", front_matter)) => yaml_positions(&format!("\n{front_matter}")),
+        Some((_, front_matter)) => toml_positions(&format!("\n{front_matter}")),
+        None => HashMap::new(),
+    };
+
+    let body_offset = source.len() - body.trim_start().len();
+
+    positions.insert(
+        CONTENT_KEY.to_string(),
+        Position::from_offset(source, body_offset),
+    );
+
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locates_yaml_values() {
+        let source = "name: Jane\njobs:\n  - title: A\n  - title: B\n    tags: [x, y]\n";
+
+        assert_eq!(
+            locate(source, &SourceType::Yaml, "name"),
+            Some(Position::new(1, 1))
+        );
+        assert_eq!(
+            locate(source, &SourceType::Yaml, "jobs[1].title"),
+            Some(Position::new(4, 5))
+        );
+        assert_eq!(
+            locate(source, &SourceType::Yaml, "jobs[1].tags[1]"),
+            Some(Position::new(5, 15))
+        );
+        // Missing values point to their closest ancestor
+        assert_eq!(
+            locate(source, &SourceType::Yaml, "jobs[0].company"),
+            Some(Position::new(3, 5))
+        );
+        assert_eq!(locate(source, &SourceType::Yaml, "email"), None);
+    }
+
+    #[test]
+    fn locates_json_values() {
+        let source = "{\n  \"name\": \"Jane\",\n  \"jobs\": [\n    { \"title\": \"A\" }\n  ]\n}\n";
+
+        assert_eq!(
+            locate(source, &SourceType::Json, "name"),
+            Some(Position::new(2, 3))
+        );
+        assert_eq!(
+            locate(source, &SourceType::Json, "jobs[0].title"),
+            Some(Position::new(4, 7))
+        );
+    }
+
+    #[test]
+    fn locates_toml_values() {
+        let source = r#"name = "Jane"
+bio = """
+title = "not a key"
+"""
+tags = [
+  "a",
+]
+
+[links]
+"my site" = "https://example.com"
+
+[[jobs]]
+title = "A"
+
+[[jobs]]
+title = "B"
+
+[jobs.company]
+name = "C"
+"#;
+
+        assert_eq!(
+            locate(source, &SourceType::Toml, "name"),
+            Some(Position::new(1, 1))
+        );
+        assert_eq!(locate(source, &SourceType::Toml, "title"), None);
+        assert_eq!(
+            locate(source, &SourceType::Toml, r#"links["my site"]"#),
+            Some(Position::new(10, 1))
+        );
+        assert_eq!(
+            locate(source, &SourceType::Toml, "jobs[1].title"),
+            Some(Position::new(16, 1))
+        );
+        assert_eq!(
+            locate(source, &SourceType::Toml, "jobs[1].company.name"),
+            Some(Position::new(19, 1))
+        );
+        assert_eq!(
+            locate(source, &SourceType::Toml, "tags[0]"),
+            Some(Position::new(5, 1))
+        );
+    }
+
+    #[test]
+    fn locates_markdown_values() {
+        let source = "---\ntitle: A\n---\n\nBody\n";
+
+        assert_eq!(
+            locate(source, &SourceType::Markdown, "title"),
+            Some(Position::new(2, 1))
+        );
+        assert_eq!(
+            locate(source, &SourceType::Markdown, "content"),
+            Some(Position::new(5, 1))
+        );
+    }
+}
@@ -1,16 +1,82 @@
 #![deny(clippy::all)]
 #![deny(clippy::pedantic)]
 
-use anyhow::Result;
-use clap::{Parser, Subcommand};
-use hot_curry::FullTemplate;
-use std::path::PathBuf;
+use anyhow::{bail, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use hot_curry::{diagnostics::code_frame, lint::Severity, types::SourceType, FullTemplate};
+use std::{fs, path::PathBuf, process};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum AnalyzeFormat {
+    Json,
+    Yaml,
+    Text,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ScaffoldFormat {
+    Yaml,
+    Json,
+    Toml,
+    Markdown,
+}
+
+impl From<ScaffoldFormat> for SourceType {
+    fn from(format: ScaffoldFormat) -> Self {
+        match format {
+            ScaffoldFormat::Yaml => SourceType::Yaml,
+            ScaffoldFormat::Json => SourceType::Json,
+            ScaffoldFormat::Toml => SourceType::Toml,
+            ScaffoldFormat::Markdown => SourceType::Markdown,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    JsonResume,
+}
 
 #[derive(Debug, Subcommand)]
 enum Subcommands {
     /// Will automatically analyze the template used by the CV and display all the expected variables,
-    /// the iterable ones, and the potentially optional ones.
-    Analyze,
+    /// the iterable ones, the potentially optional ones, and the data that is never used.
+    Analyze {
+        /// Writes the JSON Schema of the data file, inferred from the template, to this path instead
+        #[clap(long)]
+        schema: Option<PathBuf>,
+        /// The format of the analysis
+        #[clap(long, value_enum, default_value_t = AnalyzeFormat::Text)]
+        format: AnalyzeFormat,
+    },
+    /// Will check that the data file contains all the values the template expects, with the expected types,
+    /// and exit with an error otherwise. The values overridden when merging several data files are listed too.
+    Validate,
+    /// Will check the template, and the templates it uses, against the lint rules,
+    /// and exit with an error if some rule with the `error` severity is broken.
+    Lint,
+    /// Will print a data file skeleton with placeholder values, following the shape the template expects,
+    /// arrays and objects iterated with a key contain one example element, and optional values are marked
+    /// with a comment, except in json which has no comments.
+    Scaffold {
+        /// The format of the data file
+        #[clap(long, value_enum, default_value_t = ScaffoldFormat::Yaml)]
+        format: ScaffoldFormat,
+    },
+    /// Will map the data to another format, following the mapping table of the `[export]` section,
+    /// and write the result to a file.
+    Export {
+        /// The format to export the data to
+        #[clap(long, value_enum)]
+        to: ExportFormat,
+    },
+    /// Will normalise the template and print it to the standard output.
+    Fmt {
+        /// Rewrites the template file in place instead, this is refused if the template has comments,
+        /// whitespace next to trim markers, or anything else the formatted template would lose
+        #[clap(long)]
+        write: bool,
+    },
 }
 
 #[derive(Debug, Parser)]
@@ -29,36 +95,110 @@ async fn main() -> Result<()> {
 
     let args = Args::parse();
 
-    let mut full_template = FullTemplate::from_config_path(args.config_path).await?;
+    let mut full_template = match FullTemplate::from_config_path(args.config_path).await {
+        Ok(full_template) => full_template,
+        Err(err) => {
+            // Errors in the config, data, or template files are shown with the faulty lines
+            if let Some(code_frame) = err.code_frame() {
+                eprintln!("Error: {err}\n{code_frame}");
+
+                process::exit(1);
+            }
+
+            return Err(err.into());
+        }
+    };
+
+    match args.subcommand {
+        Some(Subcommands::Analyze {
+            schema: Some(schema_path),
+            ..
+        }) => {
+            let schema = serde_json::to_string_pretty(&full_template.json_schema())?;
 
-    if let Some(Subcommands::Analyze) = args.subcommand {
-        let mut visitor = full_template.visit_renderer();
+            fs::write(schema_path, schema)?;
+        }
+        Some(Subcommands::Analyze {
+            schema: None,
+            format,
+        }) => {
+            let analysis = full_template.analysis();
 
-        let sorted_idents = visitor.sorted_idents();
-        if !sorted_idents.is_empty() {
-            println!("Used variables:");
-            for ident in visitor.sorted_idents() {
-                println!("\t- {ident}");
+            match format {
+                AnalyzeFormat::Json => println!("{}", serde_json::to_string_pretty(&analysis)?),
+                AnalyzeFormat::Yaml => print!("{}", serde_yaml::to_string(&analysis)?),
+                AnalyzeFormat::Text => print!("{analysis}"),
             }
         }
+        Some(Subcommands::Validate) => {
+            let source_conflicts = full_template.source_conflicts();
 
-        let sorted_iterable_idents = visitor.sorted_iterable_idents();
-        if !sorted_iterable_idents.is_empty() {
-            println!("Iterable variables:");
-            for ident in visitor.sorted_iterable_idents() {
-                println!("\t- {ident}");
+            if !source_conflicts.is_empty() {
+                println!("Values overridden when merging the data files:");
+                for conflict in source_conflicts {
+                    println!("\t- {conflict}");
+                }
+            }
+
+            let problems = full_template.validate();
+
+            if !problems.is_empty() {
+                println!("Problems found in the data file:");
+                for problem in &problems {
+                    println!("\t- {problem}");
+                }
+
+                bail!("The data file doesn't match the template");
             }
         }
+        Some(Subcommands::Lint) => {
+            let findings = full_template.lint();
+
+            for finding in &findings {
+                println!("{finding}");
+
+                let location = &finding.location;
+
+                if let (Some(position), Some(source)) = (
+                    location.position,
+                    full_template.template_source(&location.template),
+                ) {
+                    println!("{}", code_frame(&location.template, &source, position));
+                }
+            }
 
-        let sorted_optional_idents = visitor.sorted_optional_idents();
-        if !sorted_optional_idents.is_empty() {
-            println!("Potentially optional variables:");
-            for ident in visitor.sorted_optional_idents() {
-                println!("\t- {ident}");
+            let errors = findings
+                .iter()
+                .filter(|finding| finding.severity == Severity::Error)
+                .count();
+
+            if errors > 0 {
+                bail!("The template breaks {errors} lint rule(s) with the error severity");
+            }
+        }
+        Some(Subcommands::Scaffold { format }) => {
+            print!("{}", full_template.scaffold(&format.into())?);
+        }
+        Some(Subcommands::Export {
+            to: ExportFormat::JsonResume,
+        }) => {
+            if full_template.config().export.json_resume.mapping.is_empty() {
+                bail!("The [export.json-resume.mapping] table is missing or empty");
             }
+
+            let path = full_template.export_json_resume()?;
+
+            println!("JSON Resume written to {}", path.display());
+        }
+        Some(Subcommands::Fmt { write: false }) => print!("{}", full_template.formatted_template()),
+        Some(Subcommands::Fmt { write: true }) => {
+            let Some(template_path) = full_template.template_path() else {
+                bail!("The template is fetched from a url, it can't be rewritten in place");
+            };
+
+            fs::write(template_path, full_template.lossless_formatted_template()?)?;
         }
-    } else {
-        full_template.generate_files()?;
+        None => full_template.generate_files()?,
     }
 
     Ok(())
@@ -0,0 +1,361 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::schema::{ancestor_paths, key_path};
+
+/// How arrays present in several source files are merged
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArrayStrategy {
+    /// The array of the last file replaces the previous ones
+    #[default]
+    Replace,
+    /// The items of the arrays are concatenated, in the files order
+    Append,
+    /// The objects having the same value for the key are merged, the other items are appended
+    MergeByKey(String),
+}
+
+/// A value of a source file replacing a different value of a previous file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    /// The path of the value, like `jobs[2].title`
+    pub path: String,
+    /// The file overriding the value
+    pub file: String,
+    pub previous: Value,
+    pub value: Value,
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} is overridden by {} from {}",
+            self.path, self.previous, self.value, self.file
+        )
+    }
+}
+
+/// Where a value of the merged data comes from: the file, and the path of the value in the file,
+/// which differs from the merged path when arrays are appended or merged by key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Origin {
+    pub file: String,
+    pub path: String,
+}
+
+/// The origins of the values of the merged data, recorded where a file adds or replaces a value,
+/// the descendants of a value come from the same file
+#[derive(Debug, Clone, Default)]
+pub struct Origins(BTreeMap<String, Origin>);
+
+impl Origins {
+    fn insert(&mut self, path: &str, origin: Origin) {
+        let replaced_descendants = self
+            .0
+            .range(path.to_string()..)
+            .map(|(descendant, _)| descendant)
+            .take_while(|descendant| descendant.starts_with(path))
+            .filter(|descendant| descendant[path.len()..].starts_with(['.', '[']))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for descendant in replaced_descendants {
+            self.0.remove(&descendant);
+        }
+
+        self.0.insert(path.to_string(), origin);
+    }
+
+    /// The origin of the value at `path` in the merged data, from the origin of its closest recorded ancestor
+    #[must_use]
+    pub fn find(&self, path: &str) -> Option<Origin> {
+        ancestor_paths(path).into_iter().rev().find_map(|ancestor| {
+            let origin = self.0.get(ancestor)?;
+            let descendant_path = &path[ancestor.len()..];
+
+            Some(Origin {
+                file: origin.file.clone(),
+                path: match origin.path.as_str() {
+                    "" => descendant_path.trim_start_matches('.').to_string(),
+                    origin_path => format!("{origin_path}{descendant_path}"),
+                },
+            })
+        })
+    }
+}
+
+/// Merges the objects with the same key value in `base`, appends the other items
+fn merge_arrays_by_key(
+    base: &mut Vec<Value>,
+    overlay: Vec<Value>,
+    key: &str,
+    merger: &mut Merger,
+    path: &str,
+    file_path: &str,
+) {
+    for (overlay_index, item) in overlay.into_iter().enumerate() {
+        let index = item.get(key).and_then(|item_key| {
+            base.iter()
+                .position(|base_item| base_item.get(key) == Some(item_key))
+        });
+        let item_file_path = format!("{file_path}[{overlay_index}]");
+
+        if let Some(index) = index {
+            merger.merge_value(
+                &mut base[index],
+                item,
+                &format!("{path}[{index}]"),
+                &item_file_path,
+            );
+        } else {
+            merger.record_origin(&format!("{path}[{}]", base.len()), &item_file_path);
+            base.push(item);
+        }
+    }
+}
+
+/// Deep merges the source files, in order, and keeps track of the conflicts
+#[derive(Debug)]
+pub struct Merger {
+    array_strategy: ArrayStrategy,
+    /// The file being merged
+    file: String,
+    conflicts: Vec<Conflict>,
+    origins: Origins,
+}
+
+impl Merger {
+    #[must_use]
+    pub fn new(array_strategy: ArrayStrategy) -> Self {
+        Self {
+            array_strategy,
+            file: String::new(),
+            conflicts: Vec::new(),
+            origins: Origins::default(),
+        }
+    }
+
+    /// Merges the content of `file` into `base`, objects are merged recursively,
+    /// arrays following the array strategy, and other values are replaced
+    pub fn merge(&mut self, base: &mut Value, overlay: Value, file: impl Into<String>) {
+        self.file = file.into();
+
+        self.merge_value(base, overlay, "", "");
+    }
+
+    #[must_use]
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+
+    #[must_use]
+    pub fn origins(&self) -> &Origins {
+        &self.origins
+    }
+
+    /// The conflicts, and the origins of the merged values
+    #[must_use]
+    pub fn into_parts(self) -> (Vec<Conflict>, Origins) {
+        (self.conflicts, self.origins)
+    }
+
+    fn record_origin(&mut self, path: &str, file_path: &str) {
+        self.origins.insert(
+            path,
+            Origin {
+                file: self.file.clone(),
+                path: file_path.to_string(),
+            },
+        );
+    }
+
+    /// `file_path` is the path of `overlay` in the file being merged
+    fn merge_value(&mut self, base: &mut Value, overlay: Value, path: &str, file_path: &str) {
+        match (base, overlay) {
+            (Value::Object(base), Value::Object(overlay)) => {
+                for (key, value) in overlay {
+                    let path = key_path(path, &key);
+                    let file_path = key_path(file_path, &key);
+
+                    if let Some(base_value) = base.get_mut(&key) {
+                        self.merge_value(base_value, value, &path, &file_path);
+                    } else {
+                        self.record_origin(&path, &file_path);
+                        base.insert(key, value);
+                    }
+                }
+            }
+            (Value::Array(base), Value::Array(overlay))
+                if self.array_strategy != ArrayStrategy::Replace =>
+            {
+                match self.array_strategy.clone() {
+                    ArrayStrategy::MergeByKey(key) => {
+                        merge_arrays_by_key(base, overlay, &key, self, path, file_path);
+                    }
+                    _ => {
+                        for (index, item) in overlay.into_iter().enumerate() {
+                            self.record_origin(
+                                &format!("{path}[{}]", base.len()),
+                                &format!("{file_path}[{index}]"),
+                            );
+                            base.push(item);
+                        }
+                    }
+                }
+            }
+            (base, overlay) => {
+                if *base != overlay {
+                    self.conflicts.push(Conflict {
+                        path: path.to_string(),
+                        file: self.file.clone(),
+                        previous: base.clone(),
+                        value: overlay.clone(),
+                    });
+                }
+
+                self.record_origin(path, file_path);
+                *base = overlay;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn merge(strategy: ArrayStrategy, files: Vec<Value>) -> (Value, Merger) {
+        let mut merger = Merger::new(strategy);
+        let mut data = json!({});
+
+        for (index, file) in files.into_iter().enumerate() {
+            merger.merge(&mut data, file, format!("{index}.yml"));
+        }
+
+        (data, merger)
+    }
+
+    fn origin(file: &str, path: &str) -> Origin {
+        Origin {
+            file: file.to_string(),
+            path: path.to_string(),
+        }
+    }
+
+    #[test]
+    fn replaces_arrays() {
+        let (data, merger) = merge(
+            ArrayStrategy::Replace,
+            vec![json!({ "tags": [1, 2] }), json!({ "tags": [3] })],
+        );
+
+        assert_eq!(data, json!({ "tags": [3] }));
+        assert_eq!(merger.conflicts().len(), 1);
+        assert_eq!(
+            merger.origins().find("tags[0]"),
+            Some(origin("1.yml", "tags[0]"))
+        );
+    }
+
+    #[test]
+    fn appends_arrays() {
+        let (data, merger) = merge(
+            ArrayStrategy::Append,
+            vec![json!({ "tags": [1, 2] }), json!({ "tags": [3] })],
+        );
+
+        assert_eq!(data, json!({ "tags": [1, 2, 3] }));
+        assert!(merger.conflicts().is_empty());
+        assert_eq!(
+            merger.origins().find("tags[1]"),
+            Some(origin("0.yml", "tags[1]"))
+        );
+        assert_eq!(
+            merger.origins().find("tags[2]"),
+            Some(origin("1.yml", "tags[0]"))
+        );
+    }
+
+    #[test]
+    fn merges_arrays_by_key() {
+        let (data, merger) = merge(
+            ArrayStrategy::MergeByKey("id".to_string()),
+            vec![
+                json!({ "jobs": [{ "id": 1, "title": "A" }, { "id": 2, "title": "B" }] }),
+                json!({ "jobs": [{ "id": 3, "title": "C" }, { "id": 2, "title": "D", "city": "E" }] }),
+            ],
+        );
+
+        assert_eq!(
+            data,
+            json!({ "jobs": [
+                { "id": 1, "title": "A" },
+                { "id": 2, "title": "D", "city": "E" },
+                { "id": 3, "title": "C" },
+            ] })
+        );
+        assert_eq!(
+            merger.conflicts(),
+            &[Conflict {
+                path: "jobs[1].title".to_string(),
+                file: "1.yml".to_string(),
+                previous: json!("B"),
+                value: json!("D"),
+            }]
+        );
+        assert_eq!(
+            merger.origins().find("jobs[1].city"),
+            Some(origin("1.yml", "jobs[1].city"))
+        );
+        assert_eq!(
+            merger.origins().find("jobs[2].title"),
+            Some(origin("1.yml", "jobs[0].title"))
+        );
+        assert_eq!(
+            merger.origins().find("jobs[0].title"),
+            Some(origin("0.yml", "jobs[0].title"))
+        );
+    }
+
+    #[test]
+    fn merges_objects_deeply() {
+        let (data, merger) = merge(
+            ArrayStrategy::Replace,
+            vec![
+                json!({ "basics": { "name": "A", "email": "a@b.c" } }),
+                json!({ "basics": { "name": "A", "phone": "1" } }),
+                json!({ "basics": "replaced" }),
+            ],
+        );
+
+        assert_eq!(data, json!({ "basics": "replaced" }));
+        // Merging the same value is not a conflict
+        assert_eq!(merger.conflicts().len(), 1);
+        assert_eq!(merger.conflicts()[0].previous["phone"], json!("1"));
+        // The origins recorded under a replaced value are dropped
+        assert_eq!(
+            merger.origins().find("basics.phone"),
+            Some(origin("2.yml", "basics.phone"))
+        );
+    }
+
+    #[test]
+    fn has_no_origin_for_unknown_paths() {
+        let (_, merger) = merge(ArrayStrategy::Replace, vec![json!({ "name": "A" })]);
+
+        assert_eq!(merger.origins().find("email"), None);
+        assert_eq!(
+            merger.origins().find(r#"name["a.b"]"#),
+            Some(origin("0.yml", r#"name["a.b"]"#))
+        );
+    }
+}
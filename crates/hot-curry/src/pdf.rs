@@ -0,0 +1,853 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Mutex, PoisonError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use headless_chrome::{
+    protocol::{page::PrintToPdfOptions, Method},
+    Browser, LaunchOptionsBuilder, Tab,
+};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use url::Url;
+
+use crate::{
+    errors::{Error, Result},
+    types::Document,
+};
+
+/// How often the readiness conditions are checked
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The scale limits accepted by Chrome
+const MIN_SCALE: f32 = 0.1;
+const MAX_SCALE: f32 = 2.;
+
+/// A length in inches, the unit Chrome expects,
+/// written as a number of inches, or with a unit, like `10mm`, `1.5cm`, `0.5in`, `12pt`, or `96px`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(try_from = "LengthValue")]
+pub struct Length(f32);
+
+impl Length {
+    #[must_use]
+    pub fn inches(self) -> f32 {
+        self.0
+    }
+}
+
+/// The raw lengths, so the untagged enums containing lengths keep the errors of the lengths
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LengthValue {
+    Inches(f32),
+    WithUnit(String),
+}
+
+impl TryFrom<LengthValue> for Length {
+    type Error = String;
+
+    fn try_from(value: LengthValue) -> Result<Self, Self::Error> {
+        let inches = match value {
+            LengthValue::Inches(inches) => inches,
+            LengthValue::WithUnit(length) => {
+                let unit_start = length
+                    .find(|c: char| c.is_ascii_alphabetic())
+                    .unwrap_or(length.len());
+
+                let (number, unit) = length.split_at(unit_start);
+
+                let number = number.trim().parse::<f32>().map_err(|_| {
+                    format!("invalid length `{length}`, expected a number and a unit, like `10mm`")
+                })?;
+
+                let inches_per_unit = match unit {
+                    "in" | "" => 1.,
+                    "cm" => 1. / 2.54,
+                    "mm" => 1. / 25.4,
+                    "pt" => 1. / 72.,
+                    "px" => 1. / 96.,
+                    _ => {
+                        return Err(format!(
+                            "unknown unit `{unit}` in `{length}`, expected mm, cm, in, pt, or px"
+                        ))
+                    }
+                };
+
+                number * inches_per_unit
+            }
+        };
+
+        if !inches.is_finite() || inches < 0. {
+            return Err("a length must be a positive number".to_string());
+        }
+
+        Ok(Self(inches))
+    }
+}
+
+/// The standard paper formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum PaperFormat {
+    #[serde(rename = "a3", alias = "A3")]
+    A3,
+    #[serde(rename = "a4", alias = "A4")]
+    A4,
+    #[serde(rename = "a5", alias = "A5")]
+    A5,
+    #[serde(rename = "letter", alias = "Letter")]
+    Letter,
+    #[serde(rename = "legal", alias = "Legal")]
+    Legal,
+    #[serde(rename = "tabloid", alias = "Tabloid")]
+    Tabloid,
+}
+
+impl PaperFormat {
+    /// The width and the height, in inches
+    #[must_use]
+    pub fn size(self) -> (f32, f32) {
+        match self {
+            PaperFormat::A3 => (11.69, 16.54),
+            PaperFormat::A4 => (8.27, 11.69),
+            PaperFormat::A5 => (5.83, 8.27),
+            PaperFormat::Letter => (8.5, 11.),
+            PaperFormat::Legal => (8.5, 14.),
+            PaperFormat::Tabloid => (11., 17.),
+        }
+    }
+}
+
+/// A paper format, like `"a4"`, or a custom size, like `{ width = "210mm", height = "297mm" }`
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "PaperValue")]
+pub enum Paper {
+    Format(PaperFormat),
+    Custom { width: Length, height: Length },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PaperValue {
+    Format(String),
+    Custom {
+        width: LengthValue,
+        height: LengthValue,
+    },
+}
+
+impl TryFrom<PaperValue> for Paper {
+    type Error = String;
+
+    fn try_from(value: PaperValue) -> Result<Self, Self::Error> {
+        match value {
+            PaperValue::Format(format) => PaperFormat::deserialize(
+                serde::de::value::StrDeserializer::<serde::de::value::Error>::new(&format),
+            )
+            .map(Paper::Format)
+            .map_err(|_| {
+                format!(
+                    "unknown paper format `{format}`, expected a3, a4, a5, letter, legal, tabloid, \
+                    or a custom size like {{ width = \"210mm\", height = \"297mm\" }}"
+                )
+            }),
+            PaperValue::Custom { width, height } => {
+                let width = Length::try_from(width)?;
+                let height = Length::try_from(height)?;
+
+                if width.inches() == 0. || height.inches() == 0. {
+                    return Err("the paper width and height can't be 0".to_string());
+                }
+
+                Ok(Paper::Custom { width, height })
+            }
+        }
+    }
+}
+
+impl Paper {
+    /// The width and the height, in inches
+    #[must_use]
+    pub fn size(self) -> (f32, f32) {
+        match self {
+            Paper::Format(format) => format.size(),
+            Paper::Custom { width, height } => (width.inches(), height.inches()),
+        }
+    }
+}
+
+/// The same margin for all the sides, like `"1cm"`, or per side, like `{ top = "1cm", left = "2cm" }`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(try_from = "MarginsValue")]
+pub struct Margins {
+    pub top: Length,
+    pub bottom: Length,
+    pub left: Length,
+    pub right: Length,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MarginsValue {
+    All(LengthValue),
+    Sides(MarginSides),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MarginSides {
+    top: Option<LengthValue>,
+    bottom: Option<LengthValue>,
+    left: Option<LengthValue>,
+    right: Option<LengthValue>,
+}
+
+impl TryFrom<MarginsValue> for Margins {
+    type Error = String;
+
+    fn try_from(value: MarginsValue) -> Result<Self, Self::Error> {
+        let side =
+            |length: Option<LengthValue>| length.map_or(Ok(Length::default()), Length::try_from);
+
+        match value {
+            MarginsValue::All(length) => {
+                let length = Length::try_from(length)?;
+
+                Ok(Self {
+                    top: length,
+                    bottom: length,
+                    left: length,
+                    right: length,
+                })
+            }
+            MarginsValue::Sides(MarginSides {
+                top,
+                bottom,
+                left,
+                right,
+            }) => Ok(Self {
+                top: side(top)?,
+                bottom: side(bottom)?,
+                left: side(left)?,
+                right: side(right)?,
+            }),
+        }
+    }
+}
+
+/// Chrome's scale, between 0.1 and 2
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "f32")]
+pub struct Scale(f32);
+
+impl TryFrom<f32> for Scale {
+    type Error = String;
+
+    fn try_from(scale: f32) -> Result<Self, Self::Error> {
+        if !(MIN_SCALE..=MAX_SCALE).contains(&scale) {
+            return Err(format!(
+                "the scale must be between {MIN_SCALE} and {MAX_SCALE}, found {scale}"
+            ));
+        }
+
+        Ok(Self(scale))
+    }
+}
+
+/// The pages to print, like `1-3, 5`, the pages start at 1
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct PageRanges(String);
+
+impl TryFrom<String> for PageRanges {
+    type Error = String;
+
+    fn try_from(ranges: String) -> Result<Self, Self::Error> {
+        let is_page = |page: &str| page.trim().parse::<u32>().map_or(false, |page| page > 0);
+
+        for range in ranges.split(',') {
+            let valid = match range.split_once('-') {
+                Some((start, end)) => {
+                    // Open ranges, like `-3` or `2-`, are accepted by Chrome
+                    (start.trim().is_empty() || is_page(start))
+                        && (end.trim().is_empty() || is_page(end))
+                        && !(start.trim().is_empty() && end.trim().is_empty())
+                }
+                None => is_page(range),
+            };
+
+            if !valid {
+                return Err(format!(
+                    "invalid page range `{}` in `{ranges}`, expected pages and ranges like `1-3, 5`",
+                    range.trim()
+                ));
+            }
+        }
+
+        Ok(Self(ranges))
+    }
+}
+
+/// A header or footer template, inline, or in a file like `{ path = "./footer.html" }`,
+/// rendered with Tera and the CV data. Chrome then fills the elements with the `pageNumber`,
+/// `totalPages`, `date`, `title`, and `url` classes, like `<span class="pageNumber"></span>`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum PageTemplate {
+    Inline(String),
+    Path { path: PathBuf },
+}
+
+impl PageTemplate {
+    /// The template source, the file is read relatively to `base_path`
+    pub fn read(&self, base_path: &Path) -> Result<String> {
+        match self {
+            PageTemplate::Inline(template) => Ok(template.clone()),
+            PageTemplate::Path { path } => Ok(fs::read_to_string(base_path.join(path))?),
+        }
+    }
+}
+
+/// The `[output.pdf.browser]` section
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+pub struct BrowserSettings {
+    /// The Chrome or Chromium executable, found in the `PATH` (or with the `CHROME` variable) otherwise
+    pub path: Option<PathBuf>,
+    /// Can be turned off when Chrome runs as root, in a container for instance
+    pub sandbox: bool,
+    /// In seconds
+    pub launch_timeout: u64,
+}
+
+impl Default for BrowserSettings {
+    fn default() -> Self {
+        Self {
+            path: None,
+            sandbox: true,
+            launch_timeout: 20,
+        }
+    }
+}
+
+/// The `[output.pdf.wait]` section, the conditions the page must meet before being printed,
+/// checked in order, and then the delay
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+pub struct WaitSettings {
+    /// Waits for the web fonts to be loaded, like `document.fonts.ready`
+    pub fonts: bool,
+    /// Waits for an element matching the CSS selector
+    pub selector: Option<String>,
+    /// Waits for the JavaScript expression to be truthy, the expression throwing counts as falsy
+    pub expression: Option<String>,
+    /// In milliseconds
+    pub delay: u64,
+    /// In seconds, for all the conditions
+    pub timeout: u64,
+}
+
+impl Default for WaitSettings {
+    fn default() -> Self {
+        Self {
+            fonts: false,
+            selector: None,
+            expression: None,
+            delay: 0,
+            timeout: 10,
+        }
+    }
+}
+
+/// A condition of the `[output.pdf.wait]` section, as a JavaScript expression
+#[derive(Debug, PartialEq, Eq)]
+struct WaitCondition {
+    description: String,
+    expression: String,
+    /// The expression is a promise resolving to `true`, awaited instead of polled
+    awaits_promise: bool,
+}
+
+impl WaitSettings {
+    /// The conditions, in the order they're waited for
+    fn conditions(&self) -> Vec<WaitCondition> {
+        let mut conditions = Vec::new();
+
+        if self.fonts {
+            // `document.fonts.status` can be `loaded` before the fonts used by the page start loading
+            conditions.push(WaitCondition {
+                description: "the fonts to be loaded".to_string(),
+                expression: "document.fonts.ready.then(() => true)".to_string(),
+                awaits_promise: true,
+            });
+        }
+
+        if let Some(selector) = &self.selector {
+            conditions.push(WaitCondition {
+                description: format!("an element matching `{selector}`"),
+                expression: format!(
+                    "document.querySelector({}) !== null",
+                    serde_json::Value::from(selector.as_str())
+                ),
+                awaits_promise: false,
+            });
+        }
+
+        if let Some(expression) = &self.expression {
+            conditions.push(WaitCondition {
+                description: format!("`{expression}` to be truthy"),
+                expression: expression.clone(),
+                awaits_promise: false,
+            });
+        }
+
+        conditions
+    }
+
+    /// Waits for the conditions, then for the delay, fails when the conditions aren't met before the timeout
+    fn wait(&self, tab: &Tab) -> Result<()> {
+        let deadline = Instant::now() + Duration::from_secs(self.timeout);
+
+        for condition in self.conditions() {
+            let description = &condition.description;
+
+            // The errors are returned as strings, so they can be reported on timeout
+            let expression = if condition.awaits_promise {
+                format!("({}).catch(String)", condition.expression)
+            } else {
+                format!(
+                    "(() => {{ try {{ return Boolean({}); }} catch (err) {{ return String(err); }} }})()",
+                    condition.expression
+                )
+            };
+
+            let mut last_error = None;
+
+            loop {
+                let result = tab
+                    .evaluate(&expression, condition.awaits_promise)
+                    .map_err(|err| {
+                        Error::HeadlessBrowser(format!("Couldn't wait for {description}: {err}"))
+                    })?;
+
+                match result.value {
+                    Some(serde_json::Value::Bool(true)) => break,
+                    Some(serde_json::Value::String(err)) => last_error = Some(err),
+                    _ => {}
+                }
+
+                if Instant::now() >= deadline {
+                    return Err(Error::HeadlessBrowser(format!(
+                        "Timed out after {}s waiting for {description}{}",
+                        self.timeout,
+                        last_error
+                            .map(|err| format!(", last error: {err}"))
+                            .unwrap_or_default()
+                    )));
+                }
+
+                thread::sleep(WAIT_POLL_INTERVAL);
+            }
+        }
+
+        thread::sleep(Duration::from_millis(self.delay));
+
+        Ok(())
+    }
+}
+
+/// The `[output.pdf]` section, the defaults keep the paper size to Chrome's (or the CSS one),
+/// without margins, and with the backgrounds
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+pub struct PrintSettings {
+    pub paper: Option<Paper>,
+    pub margins: Margins,
+    pub landscape: bool,
+    pub scale: Option<Scale>,
+    pub page_ranges: Option<PageRanges>,
+    /// Prints the background colors and images
+    pub background: bool,
+    /// The CSS `@page` size, when present, wins over the paper
+    pub prefer_css_page_size: bool,
+    /// Printed in the top margin of every page, which must not be 0
+    pub header: Option<PageTemplate>,
+    /// Printed in the bottom margin of every page, which must not be 0
+    pub footer: Option<PageTemplate>,
+    pub browser: BrowserSettings,
+    pub wait: WaitSettings,
+}
+
+impl Default for PrintSettings {
+    fn default() -> Self {
+        Self {
+            paper: None,
+            margins: Margins::default(),
+            landscape: false,
+            scale: None,
+            page_ranges: None,
+            background: true,
+            prefer_css_page_size: true,
+            header: None,
+            footer: None,
+            browser: BrowserSettings::default(),
+            wait: WaitSettings::default(),
+        }
+    }
+}
+
+impl PrintSettings {
+    /// Deserializes the settings, and fails when the header or the footer is set without the margin
+    /// it's printed in, as Chrome would then print it out of the page
+    pub fn deserialize_checked<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let settings = Self::deserialize(deserializer)?;
+
+        for (template, margin, name, side) in [
+            (&settings.header, settings.margins.top, "header", "top"),
+            (
+                &settings.footer,
+                settings.margins.bottom,
+                "footer",
+                "bottom",
+            ),
+        ] {
+            if template.is_some() && margin.inches() <= 0. {
+                return Err(de::Error::custom(format!(
+                    "the {name} is printed in the {side} margin, which must not be 0"
+                )));
+            }
+        }
+
+        Ok(settings)
+    }
+
+    /// The options of Chrome, with the rendered header and footer
+    #[must_use]
+    pub fn print_options(&self, header: Option<&str>, footer: Option<&str>) -> PrintToPdfOptions {
+        let (paper_width, paper_height) = self.paper.map(Paper::size).unzip();
+
+        let display_header_footer = header.is_some() || footer.is_some();
+
+        // Chrome prints its own header or footer (the date, title, url, and page number) when only one is given
+        let page_template = |template: Option<&str>| {
+            display_header_footer.then(|| template.unwrap_or("<span></span>").to_string())
+        };
+
+        PrintToPdfOptions {
+            landscape: Some(self.landscape),
+            display_header_footer: Some(display_header_footer),
+            print_background: Some(self.background),
+            scale: self.scale.map(|scale| scale.0),
+            paper_width,
+            paper_height,
+            margin_top: Some(self.margins.top.inches()),
+            margin_bottom: Some(self.margins.bottom.inches()),
+            margin_left: Some(self.margins.left.inches()),
+            margin_right: Some(self.margins.right.inches()),
+            page_ranges: self.page_ranges.as_ref().map(|ranges| ranges.0.clone()),
+            ignore_invalid_page_ranges: None,
+            header_template: page_template(header),
+            footer_template: page_template(footer),
+            prefer_css_page_size: Some(self.prefer_css_page_size),
+        }
+    }
+}
+
+/// `Target.closeTarget`, which `headless_chrome` doesn't provide
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CloseTarget {
+    target_id: String,
+}
+
+impl Method for CloseTarget {
+    const NAME: &'static str = "Target.closeTarget";
+
+    type ReturnObject = serde_json::Value;
+}
+
+fn launch(settings: &BrowserSettings) -> Result<Browser> {
+    let launch_options = LaunchOptionsBuilder::default()
+        .path(settings.path.clone())
+        .sandbox(settings.sandbox)
+        .build()
+        .map_err(|err| Error::HeadlessBrowser(format!("Browser options error: {err}")))?;
+
+    // The browser is launched in its own thread, so it can be given up on after the timeout,
+    // it's then dropped (and killed) as soon as it's launched
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || sender.send(Browser::new(launch_options)).ok());
+
+    receiver
+        .recv_timeout(Duration::from_secs(settings.launch_timeout))
+        .map_err(|_| {
+            Error::HeadlessBrowser(format!(
+                "Browser init error: not started after {}s",
+                settings.launch_timeout
+            ))
+        })?
+        .map_err(|err| Error::HeadlessBrowser(format!("Browser init error: {err}")))
+}
+
+/// Prints the documents of a generation, the browser is started with the first document,
+/// each document is printed in its own tab, and the browser is stopped when the printer is dropped
+pub struct Printer<'a> {
+    settings: &'a PrintSettings,
+    /// Where the html files are written
+    base_path: &'a Path,
+    browser: Mutex<Option<Browser>>,
+}
+
+impl<'a> Printer<'a> {
+    #[must_use]
+    pub fn new(settings: &'a PrintSettings, base_path: &'a Path) -> Self {
+        Self {
+            settings,
+            base_path,
+            browser: Mutex::new(None),
+        }
+    }
+
+    fn new_tab(&self) -> Result<Arc<Tab>> {
+        let mut browser = self.browser.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let browser = match &mut *browser {
+            Some(browser) => browser,
+            None => browser.insert(launch(&self.settings.browser)?),
+        };
+
+        browser
+            .new_tab()
+            .map_err(|err| Error::HeadlessBrowser(format!("Tab init error: {err}")))
+    }
+
+    pub fn print(&self, document: &Document) -> Result<Vec<u8>> {
+        // The html is written next to the config file, like the html output,
+        // so the relative images, fonts, and stylesheets resolve the same way
+        let mut html_file = tempfile::Builder::new()
+            .prefix(".hot-curry-")
+            .suffix(".html")
+            .tempfile_in(self.base_path)?;
+
+        html_file.write_all(document.html.as_bytes())?;
+
+        let html_path = html_file.path().canonicalize()?;
+
+        let url = Url::from_file_path(&html_path).map_err(|()| {
+            Error::HeadlessBrowser(format!("Invalid html path: {}", html_path.display()))
+        })?;
+
+        let print_options = self
+            .settings
+            .print_options(document.header.as_deref(), document.footer.as_deref());
+
+        let tab = self.new_tab()?;
+
+        let pdf = self.print_in_tab(&tab, &url, print_options);
+
+        let close = tab.call_method(CloseTarget {
+            target_id: tab.get_target_id().clone(),
+        });
+
+        if let Err(err) = close {
+            tracing::warn!("Couldn't close the tab: {err}");
+        }
+
+        pdf
+    }
+
+    fn print_in_tab(
+        &self,
+        tab: &Tab,
+        url: &Url,
+        print_options: PrintToPdfOptions,
+    ) -> Result<Vec<u8>> {
+        tab.navigate_to(url.as_str())
+            .map_err(|err| Error::HeadlessBrowser(format!("Couldn't open output html: {err}")))?;
+
+        tab.wait_until_navigated().map_err(|err| {
+            Error::HeadlessBrowser(format!("Couldn't successfully navigate to html: {err}"))
+        })?;
+
+        self.settings.wait.wait(tab)?;
+
+        tab.print_to_pdf(Some(print_options))
+            .map_err(|err| Error::HeadlessBrowser(format!("PDF printing init error: {err}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(toml: &str) -> Result<PrintSettings, toml::de::Error> {
+        PrintSettings::deserialize_checked(&mut toml::Deserializer::new(toml))
+    }
+
+    fn margin(length: &str) -> Result<f32, String> {
+        settings(&format!("margins = {length}"))
+            // Rounded, as the conversions are not exact
+            .map(|settings| (settings.margins.top.inches() * 1000.).round() / 1000.)
+            .map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn parses_lengths() {
+        assert_eq!(margin("1"), Ok(1.));
+        assert_eq!(margin(r#""0.5in""#), Ok(0.5));
+        assert_eq!(margin(r#""2.54cm""#), Ok(1.));
+        assert_eq!(margin(r#""25.4 mm""#), Ok(1.));
+        assert_eq!(margin(r#""72pt""#), Ok(1.));
+        assert_eq!(margin(r#""96px""#), Ok(1.));
+        assert_eq!(margin(r#""2""#), Ok(2.));
+    }
+
+    #[test]
+    fn rejects_invalid_lengths() {
+        assert!(margin(r#""1km""#)
+            .unwrap_err()
+            .contains("unknown unit `km`"));
+        assert!(margin(r#""mm""#)
+            .unwrap_err()
+            .contains("invalid length `mm`"));
+        assert!(margin("-1").unwrap_err().contains("positive"));
+        assert!(margin(r#""-1cm""#).unwrap_err().contains("positive"));
+    }
+
+    #[test]
+    fn parses_margins_per_side() {
+        let margins = settings(r#"margins = { top = "1in", left = 2 }"#)
+            .unwrap()
+            .margins;
+
+        assert_eq!(
+            margins,
+            Margins {
+                top: Length(1.),
+                bottom: Length(0.),
+                left: Length(2.),
+                right: Length(0.),
+            }
+        );
+        assert!(settings(r#"margins = { middle = "1in" }"#).is_err());
+    }
+
+    #[test]
+    fn parses_papers() {
+        let paper = |toml: &str| settings(toml).map(|settings| settings.paper.map(Paper::size));
+
+        assert_eq!(paper(r#"paper = "a4""#).unwrap(), Some((8.27, 11.69)));
+        assert_eq!(paper(r#"paper = "Letter""#).unwrap(), Some((8.5, 11.)));
+        assert_eq!(
+            paper(r#"paper = { width = "1in", height = 2 }"#).unwrap(),
+            Some((1., 2.))
+        );
+        assert!(paper(r#"paper = "a0""#)
+            .unwrap_err()
+            .to_string()
+            .contains("unknown paper format `a0`"));
+        assert!(paper(r#"paper = { width = 0, height = 2 }"#)
+            .unwrap_err()
+            .to_string()
+            .contains("can't be 0"));
+    }
+
+    #[test]
+    fn parses_scales() {
+        assert_eq!(settings("scale = 0.1").unwrap().scale, Some(Scale(0.1)));
+        assert_eq!(settings("scale = 2").unwrap().scale, Some(Scale(2.)));
+        assert!(settings("scale = 0").is_err());
+        assert!(settings("scale = 2.5").is_err());
+    }
+
+    #[test]
+    fn parses_page_ranges() {
+        let page_ranges = |ranges: &str| {
+            settings(&format!("page-ranges = {ranges:?}")).map(|settings| settings.page_ranges)
+        };
+
+        for ranges in ["1", "1-3, 5", "-3", "2-", " 1 - 2 ,4"] {
+            assert_eq!(
+                page_ranges(ranges).unwrap(),
+                Some(PageRanges(ranges.to_string()))
+            );
+        }
+
+        for ranges in ["", "0", "1-a", "-", "1,,2", "1-2-3"] {
+            assert!(page_ranges(ranges).is_err(), "{ranges}");
+        }
+    }
+
+    #[test]
+    fn keeps_chrome_defaults() {
+        let settings = settings("").unwrap();
+
+        assert_eq!(settings, PrintSettings::default());
+
+        let options = settings.print_options(None, None);
+
+        assert_eq!(options.display_header_footer, Some(false));
+        assert_eq!(options.paper_width, None);
+        assert_eq!(options.print_background, Some(true));
+        assert!(self::settings("unknown = 1").is_err());
+    }
+
+    #[test]
+    fn replaces_chrome_header_when_only_the_footer_is_given() {
+        let options = PrintSettings::default().print_options(None, Some("footer"));
+
+        assert_eq!(options.display_header_footer, Some(true));
+        assert_eq!(options.header_template.as_deref(), Some("<span></span>"));
+        assert_eq!(options.footer_template.as_deref(), Some("footer"));
+    }
+
+    #[test]
+    fn requires_a_margin_for_the_header_and_footer() {
+        let error = |toml: &str| settings(toml).unwrap_err().to_string();
+
+        assert!(error(r#"header = "h""#).contains("the header is printed in the top margin"));
+        assert!(error("footer = 'f'\nmargins = { top = '1cm' }")
+            .contains("the footer is printed in the bottom margin"));
+        assert!(
+            settings("header = 'h'\nfooter = 'f'\nmargins = { top = '1cm', bottom = '1cm' }")
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn lists_the_wait_conditions_in_order() {
+        let conditions = settings(
+            r#"wait = { expression = "window.ready", selector = "a[title=\"x\"]\n", fonts = true }"#,
+        )
+        .unwrap()
+        .wait
+        .conditions();
+
+        assert_eq!(
+            conditions
+                .iter()
+                .map(|condition| (condition.expression.as_str(), condition.awaits_promise))
+                .collect::<Vec<_>>(),
+            [
+                ("document.fonts.ready.then(() => true)", true),
+                (
+                    r#"document.querySelector("a[title=\"x\"]\n") !== null"#,
+                    false
+                ),
+                ("window.ready", false),
+            ]
+        );
+        assert_eq!(
+            conditions[1].description,
+            "an element matching `a[title=\"x\"]\n`"
+        );
+        assert!(settings("").unwrap().wait.conditions().is_empty());
+    }
+}
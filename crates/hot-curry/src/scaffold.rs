@@ -0,0 +1,313 @@
+use serde_json::{json, Map, Value};
+
+use crate::{errors::Result, types::SourceType};
+
+const HEADER_COMMENT: &str =
+    "Sample data generated from the template, replace the placeholders with your own data";
+
+const OPTIONAL_COMMENT: &str = " # optional";
+
+/// The example entry of the objects iterated with `{% for key, value in object %}`
+const EXAMPLE_KEY: &str = "key";
+
+#[derive(Debug)]
+enum Skeleton {
+    /// A placeholder value, like `""` or `0`
+    Scalar(Value),
+    /// An array with one example element
+    Array(Box<Skeleton>),
+    /// The properties, and whether they're optional
+    Object(Vec<(String, Skeleton, bool)>),
+}
+
+impl Skeleton {
+    fn from_schema(schema: &Value) -> Self {
+        match schema.get("type").and_then(Value::as_str) {
+            Some("array") => Skeleton::Array(Box::new(
+                schema
+                    .get("items")
+                    .map_or(Skeleton::Scalar(json!("")), Skeleton::from_schema),
+            )),
+            Some("object") => {
+                let required = schema
+                    .get("required")
+                    .and_then(Value::as_array)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+
+                let mut properties: Vec<_> = schema
+                    .get("properties")
+                    .and_then(Value::as_object)
+                    .into_iter()
+                    .flatten()
+                    .map(|(key, schema)| {
+                        (
+                            key.clone(),
+                            Skeleton::from_schema(schema),
+                            !required.contains(&json!(key)),
+                        )
+                    })
+                    .collect();
+
+                // An object iterated with a key, its values follow `additionalProperties`
+                if let Some(values_schema @ Value::Object(_)) = schema.get("additionalProperties") {
+                    if properties.iter().all(|(key, _, _)| key != EXAMPLE_KEY) {
+                        properties.push((
+                            EXAMPLE_KEY.to_string(),
+                            Skeleton::from_schema(values_schema),
+                            false,
+                        ));
+                    }
+                }
+
+                Skeleton::Object(properties)
+            }
+            Some("number") => Skeleton::Scalar(json!(0)),
+            _ => Skeleton::Scalar(json!("")),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        match self {
+            Skeleton::Scalar(value) => value.clone(),
+            Skeleton::Array(item) => Value::Array(vec![item.to_json()]),
+            Skeleton::Object(properties) => Value::Object(
+                properties
+                    .iter()
+                    .map(|(key, value, _)| (key.clone(), value.to_json()))
+                    .collect::<Map<_, _>>(),
+            ),
+        }
+    }
+}
+
+/// Keys are quoted unless they only contain letters, digits, and underscores
+fn format_key(key: &str) -> String {
+    if !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        key.to_string()
+    } else {
+        json!(key).to_string()
+    }
+}
+
+fn optional_comment(optional: bool) -> &'static str {
+    if optional {
+        OPTIONAL_COMMENT
+    } else {
+        ""
+    }
+}
+
+fn write_yaml_properties(
+    properties: &[(String, Skeleton, bool)],
+    indent: usize,
+    output: &mut String,
+) {
+    for (key, value, optional) in properties {
+        let comment = optional_comment(*optional);
+
+        output.push_str(&format!("{:indent$}{}:", "", format_key(key)));
+
+        match value {
+            Skeleton::Scalar(value) => output.push_str(&format!(" {value}{comment}\n")),
+            Skeleton::Object(properties) if properties.is_empty() => {
+                output.push_str(&format!(" {{}}{comment}\n"));
+            }
+            Skeleton::Object(properties) => {
+                output.push_str(&format!("{comment}\n"));
+                write_yaml_properties(properties, indent + 2, output);
+            }
+            Skeleton::Array(item) => {
+                output.push_str(&format!("{comment}\n"));
+                write_yaml_item(item, indent + 2, output);
+            }
+        }
+    }
+}
+
+fn write_yaml_item(item: &Skeleton, indent: usize, output: &mut String) {
+    match item {
+        Skeleton::Scalar(value) => output.push_str(&format!("{:indent$}- {value}\n", "")),
+        Skeleton::Object(properties) if properties.is_empty() => {
+            output.push_str(&format!("{:indent$}- {{}}\n", ""));
+        }
+        // The first property goes on the same line as the dash
+        Skeleton::Object(properties) => {
+            let mut item_output = String::new();
+
+            write_yaml_properties(properties, indent + 2, &mut item_output);
+
+            output.push_str(&format!("{:indent$}- {}", "", &item_output[indent + 2..]));
+        }
+        Skeleton::Array(item) => {
+            output.push_str(&format!("{:indent$}-\n", ""));
+            write_yaml_item(item, indent + 2, output);
+        }
+    }
+}
+
+fn toml_inline_value(value: &Skeleton) -> String {
+    match value {
+        Skeleton::Scalar(value) => value.to_string(),
+        Skeleton::Array(item) => format!("[{}]", toml_inline_value(item)),
+        Skeleton::Object(properties) => {
+            let properties = properties
+                .iter()
+                .map(|(key, value, _)| {
+                    format!("{} = {}", format_key(key), toml_inline_value(value))
+                })
+                .collect::<Vec<_>>();
+
+            format!("{{ {} }}", properties.join(", "))
+        }
+    }
+}
+
+/// Tables must come after the plain values of their parent table
+fn write_toml_table(path: &[String], properties: &[(String, Skeleton, bool)], output: &mut String) {
+    for (key, value, optional) in properties {
+        match value {
+            Skeleton::Object(properties) if !properties.is_empty() => {}
+            Skeleton::Array(item) if matches!(&**item, Skeleton::Object(_)) => {}
+            _ => output.push_str(&format!(
+                "{} = {}{}\n",
+                format_key(key),
+                toml_inline_value(value),
+                optional_comment(*optional)
+            )),
+        }
+    }
+
+    for (key, value, optional) in properties {
+        let mut table_path = path.to_vec();
+
+        table_path.push(format_key(key));
+
+        let (header, properties) = match value {
+            Skeleton::Object(properties) if !properties.is_empty() => {
+                (format!("[{}]", table_path.join(".")), properties)
+            }
+            Skeleton::Array(item) => match &**item {
+                Skeleton::Object(properties) => {
+                    (format!("[[{}]]", table_path.join(".")), properties)
+                }
+                _ => continue,
+            },
+            _ => continue,
+        };
+
+        output.push_str(&format!("\n{header}{}\n", optional_comment(*optional)));
+        write_toml_table(&table_path, properties, output);
+    }
+}
+
+/// Generates a placeholder data file following the JSON Schema inferred from the template,
+/// arrays and objects iterated with a key contain one example element, and optional values are marked
+/// with a comment, except in json which has no comments and no standard way to mark optional values
+pub fn scaffold(schema: &Value, source_type: &SourceType) -> Result<String> {
+    let skeleton = Skeleton::from_schema(schema);
+
+    let properties = match &skeleton {
+        Skeleton::Object(properties) => properties.as_slice(),
+        _ => &[],
+    };
+
+    let output = match source_type {
+        SourceType::Json | SourceType::JsonResume => {
+            format!("{}\n", serde_json::to_string_pretty(&skeleton.to_json())?)
+        }
+        SourceType::Yaml => {
+            let mut output = format!("# {HEADER_COMMENT}\n");
+
+            write_yaml_properties(properties, 0, &mut output);
+
+            output
+        }
+        // The data goes in the yaml front matter
+        SourceType::Markdown => {
+            let mut output = format!("---\n# {HEADER_COMMENT}\n");
+
+            write_yaml_properties(properties, 0, &mut output);
+
+            output.push_str("---\n");
+
+            output
+        }
+        SourceType::Toml => {
+            let mut output = format!("# {HEADER_COMMENT}\n");
+
+            write_toml_table(&[], properties, &mut output);
+
+            output
+        }
+    };
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "website": { "type": "string" },
+                "jobs": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": { "title": { "type": "string" } },
+                        "required": ["title"],
+                    },
+                },
+                "skills": { "type": "object", "additionalProperties": { "type": "number" } },
+            },
+            "required": ["jobs", "name", "skills"],
+        })
+    }
+
+    #[test]
+    fn scaffolds_yaml() {
+        assert_eq!(
+            scaffold(&schema(), &SourceType::Yaml).unwrap(),
+            format!(
+                "# {HEADER_COMMENT}\njobs:\n  - title: \"\"\nname: \"\"\nskills:\n  key: 0\nwebsite: \"\" # optional\n"
+            )
+        );
+    }
+
+    #[test]
+    fn scaffolds_toml() {
+        assert_eq!(
+            scaffold(&schema(), &SourceType::Toml).unwrap(),
+            format!(
+                "# {HEADER_COMMENT}\nname = \"\"\nwebsite = \"\" # optional\n\n[[jobs]]\ntitle = \"\"\n\n[skills]\nkey = 0\n"
+            )
+        );
+    }
+
+    #[test]
+    fn scaffolds_json_without_optional_markers() {
+        let output = scaffold(&schema(), &SourceType::Json).unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<Value>(&output).unwrap(),
+            json!({ "jobs": [{ "title": "" }], "name": "", "skills": { "key": 0 }, "website": "" })
+        );
+    }
+
+    #[test]
+    fn scaffolds_an_empty_key_value_object() {
+        let schema = json!({
+            "type": "object",
+            "properties": { "links": { "type": "object", "additionalProperties": {} } },
+        });
+
+        assert!(scaffold(&schema, &SourceType::Yaml)
+            .unwrap()
+            .ends_with("links: # optional\n  key: \"\"\n"));
+    }
+}
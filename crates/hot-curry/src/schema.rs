@@ -0,0 +1,468 @@
+use std::collections::BTreeMap;
+
+use serde_json::{json, Map, Value};
+
+use crate::visitor::TeraVariableVisitor;
+
+const JSON_SCHEMA_DRAFT: &str = "http://json-schema.org/draft-07/schema#";
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Segment {
+    /// `.name` or `["name"]`
+    Key(String),
+    /// `[]` (the items of a loop) or `[0]`
+    Items,
+}
+
+/// Parses a data path like `jobs[].tags[0]["label"]`,
+/// the parsing stops at the first dynamic access like `jobs[index]`
+pub(crate) fn parse_path(path: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut rest = path;
+
+    loop {
+        let end = rest.find(['.', '[']).unwrap_or(rest.len());
+        let (key, tail) = rest.split_at(end);
+
+        if !key.is_empty() {
+            segments.push(Segment::Key(key.to_string()));
+        }
+
+        if let Some(tail) = tail.strip_prefix('.') {
+            rest = tail;
+        } else if let Some(tail) = tail.strip_prefix('[') {
+            let index = tail.trim_start();
+
+            // Like in Tera, the quoted keys have no escapes, and can contain anything but their quote
+            let quote = index
+                .chars()
+                .next()
+                .filter(|c| matches!(c, '"' | '\'' | '`'));
+
+            let (segment, tail) = if let Some(quote) = quote {
+                let Some((key, tail)) = index[1..].split_once(quote) else {
+                    break;
+                };
+
+                (Segment::Key(key.to_string()), tail.trim_start())
+            } else {
+                let Some(close) = tail.find(']') else {
+                    break;
+                };
+
+                let (index, tail) = tail.split_at(close);
+
+                if !index.trim().chars().all(|c| c.is_ascii_digit()) {
+                    break;
+                }
+
+                (Segment::Items, tail)
+            };
+
+            let Some(tail) = tail.strip_prefix(']') else {
+                break;
+            };
+
+            segments.push(segment);
+            rest = tail;
+        } else {
+            break;
+        }
+    }
+
+    segments
+}
+
+/// Appends a key to a data path, using the `["key"]` notation when the key is not a valid identifier,
+/// so the path can be read back by [`parse_path`], the key is quoted with a quote it doesn't contain,
+/// as Tera has no escapes
+pub(crate) fn key_path(path: &str, key: &str) -> String {
+    let is_ident = key
+        .chars()
+        .next()
+        .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if !is_ident {
+        // A key containing all the quotes can't be written in Tera, it's not read back
+        let quote = ['"', '\'', '`']
+            .into_iter()
+            .find(|&quote| !key.contains(quote))
+            .unwrap_or('"');
+
+        format!("{path}[{quote}{key}{quote}]")
+    } else if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+/// The path and its ancestors, from the root: `a`, `a.b`, `a.b[0]`, quoted keys are not split
+pub(crate) fn ancestor_paths(path: &str) -> Vec<&str> {
+    let mut paths = Vec::new();
+    let mut quote = None;
+
+    for (index, c) in path.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            None if matches!(c, '"' | '\'' | '`') => quote = Some(c),
+            None if index > 0 && matches!(c, '.' | '[') => paths.push(&path[..index]),
+            _ => {}
+        }
+    }
+
+    paths.push(path);
+
+    paths
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ScalarType {
+    String,
+    Number,
+}
+
+#[derive(Debug, Default)]
+struct SchemaNode {
+    properties: BTreeMap<String, SchemaNode>,
+    items: Option<Box<SchemaNode>>,
+    iterable: bool,
+    /// Iterated with a key, `{% for key, value in node %}`, so an object whose values are the items
+    keyed: bool,
+    scalar_type: Option<ScalarType>,
+    /// Set if the path of this node is used directly by the template
+    required: Option<bool>,
+    /// Set if some descendant is required, which requires this node too
+    required_by_descendant: bool,
+}
+
+impl SchemaNode {
+    fn is_required(&self) -> bool {
+        self.required.unwrap_or(self.required_by_descendant)
+    }
+
+    fn child(&mut self, segment: Segment) -> &mut SchemaNode {
+        match segment {
+            Segment::Key(key) => self.properties.entry(key).or_default(),
+            Segment::Items => self.items.get_or_insert_with(Box::default),
+        }
+    }
+
+    fn node(&mut self, path: &str) -> &mut SchemaNode {
+        parse_path(path)
+            .into_iter()
+            .fold(self, |node, segment| node.child(segment))
+    }
+
+    fn insert_required(&mut self, path: &str, required: bool) {
+        let mut node = self;
+
+        for segment in parse_path(path) {
+            node.required_by_descendant |= required;
+            node = node.child(segment);
+        }
+
+        node.required = Some(
+            node.required
+                .map_or(required, |previous| previous && required),
+        );
+    }
+
+    fn to_json_schema(&self) -> Value {
+        let mut schema = Map::new();
+
+        if self.keyed {
+            schema.insert("type".to_string(), json!("object"));
+
+            let values_schema = self
+                .items
+                .as_ref()
+                .map_or_else(|| json!({}), |items| items.to_json_schema());
+
+            schema.insert("additionalProperties".to_string(), values_schema);
+        } else if self.iterable || self.items.is_some() {
+            schema.insert("type".to_string(), json!("array"));
+
+            if let Some(items) = &self.items {
+                schema.insert("items".to_string(), items.to_json_schema());
+            }
+        } else if !self.properties.is_empty() {
+            schema.insert("type".to_string(), json!("object"));
+        } else if let Some(scalar_type) = self.scalar_type {
+            let type_ = match scalar_type {
+                ScalarType::String => "string",
+                ScalarType::Number => "number",
+            };
+
+            schema.insert("type".to_string(), json!(type_));
+        }
+
+        if !self.properties.is_empty() {
+            let properties = self
+                .properties
+                .iter()
+                .map(|(key, node)| (key.clone(), node.to_json_schema()))
+                .collect::<Map<_, _>>();
+
+            let required = self
+                .properties
+                .iter()
+                .filter(|(_, node)| node.is_required())
+                .map(|(key, _)| json!(key))
+                .collect::<Vec<_>>();
+
+            schema.insert("properties".to_string(), Value::Object(properties));
+
+            if !required.is_empty() {
+                schema.insert("required".to_string(), Value::Array(required));
+            }
+        }
+
+        Value::Object(schema)
+    }
+}
+
+/// Infers the JSON Schema the data file must follow from the variables used in the template
+#[allow(clippy::module_name_repetitions)]
+#[must_use]
+pub fn infer_json_schema(visitor: &TeraVariableVisitor) -> Value {
+    let mut root = SchemaNode::default();
+
+    let optional_idents = visitor.optional_idents();
+
+    for ident in visitor.idents() {
+        root.insert_required(ident, !optional_idents.contains(ident));
+    }
+
+    for ident in visitor.iterable_idents() {
+        root.node(ident).iterable = true;
+    }
+
+    for ident in visitor.keyed_iterable_idents() {
+        root.node(ident).keyed = true;
+    }
+
+    for ident in visitor.number_idents() {
+        root.node(ident).scalar_type = Some(ScalarType::Number);
+    }
+
+    // Conflicting hints are resolved in favour of strings
+    for ident in visitor.string_idents() {
+        root.node(ident).scalar_type = Some(ScalarType::String);
+    }
+
+    let mut schema = Map::new();
+
+    schema.insert("$schema".to_string(), json!(JSON_SCHEMA_DRAFT));
+
+    if let Value::Object(root_schema) = root.to_json_schema() {
+        schema.extend(root_schema);
+    }
+
+    // The data file is always an object, even if the template doesn't use any variable
+    schema.insert("type".to_string(), json!("object"));
+
+    Value::Object(schema)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tera::Template;
+
+    use super::*;
+
+    fn infer(source: &str) -> Value {
+        let template = Template::new("", None, source).unwrap();
+        let mut visitor = TeraVariableVisitor::new();
+
+        visitor.visit_template(&template);
+
+        infer_json_schema(&visitor)
+    }
+
+    #[test]
+    fn parses_paths() {
+        assert_eq!(
+            parse_path(r#"jobs[].tags[0]["first name"].x"#),
+            vec![
+                Segment::Key("jobs".to_string()),
+                Segment::Items,
+                Segment::Key("tags".to_string()),
+                Segment::Items,
+                Segment::Key("first name".to_string()),
+                Segment::Key("x".to_string()),
+            ]
+        );
+        assert_eq!(
+            parse_path("jobs[index].title"),
+            vec![Segment::Key("jobs".to_string())]
+        );
+        assert_eq!(parse_path("jobs[0"), vec![Segment::Key("jobs".to_string())]);
+        assert_eq!(
+            parse_path(r#"a[ 'b]' ][ 1 ]"#),
+            vec![
+                Segment::Key("a".to_string()),
+                Segment::Key("b]".to_string()),
+                Segment::Items
+            ]
+        );
+        assert_eq!(
+            parse_path(r#"a["b"c]"#),
+            vec![Segment::Key("a".to_string())]
+        );
+        assert!(parse_path("").is_empty());
+    }
+
+    #[test]
+    fn key_paths_are_parsed_back() {
+        assert_eq!(key_path("", "name"), "name");
+        assert_eq!(key_path("jobs[2]", "title"), "jobs[2].title");
+        assert_eq!(key_path("links", "my site"), r#"links["my site"]"#);
+        assert_eq!(key_path("", "2023"), r#"["2023"]"#);
+        assert_eq!(key_path("", r#"say "hi""#), r#"['say "hi"']"#);
+
+        for key in [
+            "my site",
+            "a]b",
+            "[\"x\"]",
+            "l'été",
+            "日本語",
+            "tab\tand\nnewline\u{1}",
+            r#"a"b'c"#,
+            "",
+        ] {
+            assert_eq!(
+                parse_path(&key_path("links", key)),
+                vec![
+                    Segment::Key("links".to_string()),
+                    Segment::Key(key.to_string())
+                ],
+                "{key:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn splits_ancestor_paths() {
+        assert_eq!(
+            ancestor_paths(r#"jobs[1]["a.b"].c"#),
+            vec![
+                "jobs",
+                "jobs[1]",
+                r#"jobs[1]["a.b"]"#,
+                r#"jobs[1]["a.b"].c"#
+            ]
+        );
+        assert_eq!(ancestor_paths("name"), vec!["name"]);
+    }
+
+    #[test]
+    fn key_value_loops_iterate_on_objects() {
+        let schema =
+            infer("{% for name, level in skills %}{{ name }}{{ level.value | round }}{% endfor %}");
+
+        assert_eq!(
+            schema["properties"]["skills"],
+            json!({
+                "type": "object",
+                "additionalProperties": {
+                    "type": "object",
+                    "properties": { "value": { "type": "number" } },
+                    "required": ["value"],
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn infers_required_and_optional_values() {
+        let schema = infer(
+            "{{ name }}{% if email %}{{ email }}{% endif %}\
+             {% if links %}{{ links.github }}{% endif %}{{ address.city }}",
+        );
+
+        assert_eq!(
+            schema,
+            json!({
+                "$schema": JSON_SCHEMA_DRAFT,
+                "type": "object",
+                "properties": {
+                    "address": {
+                        "type": "object",
+                        "properties": { "city": {} },
+                        "required": ["city"],
+                    },
+                    "email": {},
+                    // The values only read in the body of an `if` are optional
+                    "links": {
+                        "type": "object",
+                        "properties": { "github": {} },
+                    },
+                    "name": {},
+                },
+                "required": ["address", "name"],
+            })
+        );
+    }
+
+    #[test]
+    fn infers_optional_values_from_defaults_and_defined_tests() {
+        let schema = infer(
+            "{{ nickname | default(value=name) }}{{ title | upper | default(value='') }}\
+             {% if photo is defined %}{% endif %}{{ photo }}{% if bio is not undefined %}{% endif %}{{ bio }}",
+        );
+
+        // `default` is only applied to missing values when it's the first filter
+        assert_eq!(schema["required"], json!(["name", "title"]));
+        assert_eq!(schema["properties"]["nickname"], json!({}));
+    }
+
+    #[test]
+    fn infers_scalar_types_from_filters_and_math() {
+        let schema = infer(
+            "{{ name | upper }}{{ age + 1 }}{{ score | round }}{{ title | upper }}{{ title + 1 }}",
+        );
+
+        assert_eq!(schema["properties"]["name"], json!({ "type": "string" }));
+        assert_eq!(schema["properties"]["age"], json!({ "type": "number" }));
+        assert_eq!(schema["properties"]["score"], json!({ "type": "number" }));
+        // Conflicting hints are resolved in favour of strings
+        assert_eq!(schema["properties"]["title"], json!({ "type": "string" }));
+    }
+
+    #[test]
+    fn infers_arrays_from_loops() {
+        let schema = infer(
+            "{% for job in jobs %}{{ job.title }}{% for tag in job.tags %}{{ tag }}{% endfor %}{% endfor %}\
+             {% for i in range(end=3) %}{{ i }}{% endfor %}{% for x in empty %}{% endfor %}",
+        );
+
+        assert_eq!(
+            schema["properties"],
+            json!({
+                "empty": { "type": "array" },
+                "jobs": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "tags": { "type": "array", "items": {} },
+                            "title": {},
+                        },
+                        "required": ["tags", "title"],
+                    },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn infers_an_empty_object_without_variables() {
+        assert_eq!(
+            infer("Hello"),
+            json!({ "$schema": JSON_SCHEMA_DRAFT, "type": "object" })
+        );
+    }
+}
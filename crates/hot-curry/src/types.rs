@@ -1,33 +1,65 @@
-use headless_chrome::{protocol::page::PrintToPdfOptions, Browser};
 use serde::{
-    de::{self, Unexpected},
+    de::{self, value::MapAccessDeserializer, MapAccess, SeqAccess, Unexpected},
     Deserialize, Deserializer,
 };
 use std::{
+    collections::{BTreeMap, HashMap},
     ffi::OsStr,
     fmt::{self, Formatter},
-    fs::File,
+    fs::{self, File},
     io::Write,
     path::{Path, PathBuf},
 };
 use tera::Context as TeraContext;
 use url::Url;
 
-use crate::errors::{Error, Result};
+use crate::{
+    diagnostics::display_path,
+    errors::{Error, Result},
+    front_matter, json_resume,
+    lint::{Rule, Severity},
+    locate,
+    merge::ArrayStrategy,
+    pdf::{PrintSettings, Printer},
+};
 
-#[derive(Debug)]
+/// The key of the tags of the data entries, used by the variants
+const TAGS_KEY: &str = "tags";
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum SourceType {
     Yaml,
     Json,
+    /// Json following the JSON Resume schema, validated when parsed, only used when set explicitly
+    JsonResume,
     Toml,
+    /// Markdown with a yaml or toml front matter, the body is exposed as `content`
+    Markdown,
 }
 
 impl SourceType {
     pub fn to_value(&self, source: impl AsRef<str>) -> Result<Value> {
         let value = match self {
             SourceType::Json => serde_json::from_str(source.as_ref())?,
+            SourceType::JsonResume => {
+                let value = serde_json::from_str(source.as_ref())?;
+
+                let mut problems = json_resume::validate(&value)?;
+
+                for problem in &mut problems {
+                    problem.position = locate::locate(source.as_ref(), self, &problem.path);
+                }
+
+                if !problems.is_empty() {
+                    return Err(Error::JsonResume(problems));
+                }
+
+                value
+            }
             SourceType::Yaml => serde_yaml::from_str(source.as_ref())?,
             SourceType::Toml => toml::from_str(source.as_ref())?,
+            SourceType::Markdown => front_matter::parse(source.as_ref())?,
         };
 
         Ok(Value(value))
@@ -37,12 +69,31 @@ impl SourceType {
 #[derive(Debug)]
 pub struct Value(serde_json::Value);
 
+impl From<serde_json::Value> for Value {
+    fn from(value: serde_json::Value) -> Self {
+        Self(value)
+    }
+}
+
 impl Value {
+    #[must_use]
+    pub fn into_json(self) -> serde_json::Value {
+        self.0
+    }
+
     pub fn into_tera_context(self) -> std::result::Result<TeraContext, tera::Error> {
         TeraContext::from_value(self.0)
     }
 }
 
+/// A rendered CV, with its rendered pdf header and footer
+#[derive(Debug)]
+pub struct Document {
+    pub html: String,
+    pub header: Option<String>,
+    pub footer: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub enum OutputType {
     #[serde(rename = "pdf")]
@@ -52,60 +103,20 @@ pub enum OutputType {
 }
 
 impl OutputType {
-    pub fn write(&self, output_base_path: impl AsRef<str>, output: impl AsRef<str>) -> Result<()> {
+    pub fn write(
+        &self,
+        output_base_path: impl AsRef<str>,
+        document: &Document,
+        printer: &Printer,
+    ) -> Result<()> {
         match self {
             OutputType::Html => {
                 let mut file = File::create(format!("{}.html", output_base_path.as_ref()))?;
 
-                file.write_all(output.as_ref().as_bytes())?;
+                file.write_all(document.html.as_bytes())?;
             }
             OutputType::Pdf => {
-                let browser = Browser::default()
-                    .map_err(|err| Error::HeadlessBrowser(format!("Browser init error: {err}")))?;
-
-                let tab = browser
-                    .wait_for_initial_tab()
-                    .map_err(|err| Error::HeadlessBrowser(format!("Tab init error: {err}")))?;
-
-                let clean_output = output
-                    .as_ref()
-                    .replace('%', "%25")
-                    .replace('&', "%26")
-                    .replace('#', "%23")
-                    .replace('"', "%22")
-                    .replace('\'', "%27");
-
-                let url = Url::parse(&format!("data:text/html;charset=UTF-8,{clean_output}"))?;
-
-                tab.navigate_to(url.as_str()).map_err(|err| {
-                    Error::HeadlessBrowser(format!("Couldn't open output html: {err}"))
-                })?;
-
-                tab.wait_until_navigated().map_err(|err| {
-                    Error::HeadlessBrowser(format!("Couldn't successfully navigate to html: {err}"))
-                })?;
-
-                let pdf = tab
-                    .print_to_pdf(Some(PrintToPdfOptions {
-                        landscape: Some(false),
-                        display_header_footer: Some(false),
-                        print_background: Some(true),
-                        scale: None,
-                        paper_width: None,
-                        paper_height: None,
-                        margin_top: Some(0.),
-                        margin_bottom: Some(0.),
-                        margin_left: Some(0.),
-                        margin_right: Some(0.),
-                        page_ranges: None,
-                        ignore_invalid_page_ranges: None,
-                        header_template: None,
-                        footer_template: None,
-                        prefer_css_page_size: Some(true),
-                    }))
-                    .map_err(|err| {
-                        Error::HeadlessBrowser(format!("PDF printing init error: {err}"))
-                    })?;
+                let pdf = printer.print(document)?;
 
                 let mut file = File::create(format!("{}.pdf", output_base_path.as_ref()))?;
 
@@ -124,11 +135,25 @@ pub struct SourceFile {
 }
 
 impl SourceFile {
-    fn deserialize<'de, D>(deserializer: D) -> Result<SourceFile, D::Error>
+    /// Reads and parses the file, relatively to `base_path`
+    pub fn read(&self, base_path: &Path) -> Result<serde_json::Value> {
+        let path = base_path.join(&self.path);
+
+        let content = fs::read_to_string(&path)?;
+
+        let value = self
+            .type_
+            .to_value(content)
+            .map_err(|err| err.with_path(display_path(&path)))?;
+
+        Ok(value.into_json())
+    }
+
+    fn deserialize_many<'de, D>(deserializer: D) -> Result<Vec<SourceFile>, D::Error>
     where
         D: Deserializer<'de>,
     {
-        deserializer.deserialize_string(SourceFileVisitor)
+        deserializer.deserialize_any(SourceFilesVisitor)
     }
 }
 
@@ -140,7 +165,8 @@ impl<'de> de::Visitor<'de> for SourceFileVisitor {
     fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
         write!(
             formatter,
-            "a valid path with one of the following extensions: json, toml, or yml/yaml"
+            "a valid path with one of the following extensions: json, toml, yml/yaml, or md, \
+            or a {{ path, type }} table"
         )
     }
 
@@ -159,6 +185,7 @@ impl<'de> de::Visitor<'de> for SourceFileVisitor {
             "json" => Ok(SourceType::Json),
             "toml" => Ok(SourceType::Toml),
             "yml" | "yaml" => Ok(SourceType::Yaml),
+            "md" => Ok(SourceType::Markdown),
             _ => Err(de::Error::invalid_value(Unexpected::Str(s), &self)),
         }?;
 
@@ -167,12 +194,138 @@ impl<'de> de::Visitor<'de> for SourceFileVisitor {
             type_: source_type,
         })
     }
+
+    /// `{ path = "resume.json", type = "json-resume" }`, the type is found from the extension if missing
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let table = SourceFileTable::deserialize(MapAccessDeserializer::new(map))?;
+
+        match table.type_ {
+            Some(type_) => Ok(SourceFile {
+                path: table.path,
+                type_,
+            }),
+            None => self.visit_str(&table.path),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SourceFileTable {
+    path: String,
+    #[serde(rename = "type")]
+    type_: Option<SourceType>,
+}
+
+impl<'de> Deserialize<'de> for SourceFile {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(SourceFileVisitor)
+    }
+}
+
+/// One path, or a list of paths
+pub struct SourceFilesVisitor;
+
+impl<'de> de::Visitor<'de> for SourceFilesVisitor {
+    type Value = Vec<SourceFile>;
+
+    fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "a valid path, or a list of valid paths, with one of the following extensions: json, toml, yml/yaml, or md, \
+            or {{ path, type }} tables"
+        )
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(vec![SourceFileVisitor.visit_str(s)?])
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        Ok(vec![SourceFileVisitor.visit_map(map)?])
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut files = Vec::new();
+
+        while let Some(file) = seq.next_element::<SourceFile>()? {
+            files.push(file);
+        }
+
+        Ok(files)
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Source {
-    #[serde(rename = "path", deserialize_with = "SourceFile::deserialize")]
-    pub file: SourceFile,
+    /// The data files, deep merged in order when there are several of them
+    #[serde(rename = "path", deserialize_with = "SourceFile::deserialize_many")]
+    pub files: Vec<SourceFile>,
+    /// How the arrays present in several files are merged
+    #[serde(default)]
+    pub arrays: ArrayStrategy,
+    /// The directories of markdown files, each one loaded into an array at its key, like `[source.directories.jobs]`
+    #[serde(default)]
+    pub directories: BTreeMap<String, SourceDirectory>,
+}
+
+/// A directory of markdown files, one per entry, like one per job
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SourceDirectory {
+    pub path: PathBuf,
+    /// The front matter key the entries are sorted by, the entries are sorted by file name otherwise
+    pub sort_by: Option<String>,
+    /// The entries without the front matter key still come last
+    #[serde(default)]
+    pub descending: bool,
+}
+
+impl SourceDirectory {
+    /// Reads and parses the markdown files of the directory, relatively to `base_path`, in order
+    pub fn read(&self, base_path: &Path) -> Result<serde_json::Value> {
+        let mut paths = fs::read_dir(base_path.join(&self.path))?
+            .map(|entry| Ok(self.path.join(entry?.file_name())))
+            .collect::<Result<Vec<_>>>()?;
+
+        paths.retain(|path| path.extension() == Some(OsStr::new("md")));
+        paths.sort();
+
+        let mut entries = paths
+            .into_iter()
+            .map(|path| {
+                SourceFile {
+                    path: path.to_string_lossy().into_owned(),
+                    type_: SourceType::Markdown,
+                }
+                .read(base_path)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        match &self.sort_by {
+            Some(key) => entries
+                .sort_by(|a, b| front_matter::compare(a.get(key), b.get(key), self.descending)),
+            None if self.descending => entries.reverse(),
+            None => {}
+        }
+
+        Ok(serde_json::Value::Array(entries))
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -192,6 +345,99 @@ pub struct Template {
 pub struct Output {
     pub path: PathBuf,
     pub types: Vec<OutputType>,
+    /// The `[output.pdf]` section, the paper, margins, and other printing options
+    #[serde(default, deserialize_with = "PrintSettings::deserialize_checked")]
+    pub pdf: PrintSettings,
+}
+
+/// A language the CV is rendered in, like `[i18n.fr]`
+#[derive(Debug, Deserialize)]
+pub struct Language {
+    /// Data merged over the source data, like the translated job descriptions
+    pub overlay: Option<SourceFile>,
+    /// The table used by the `t` function
+    pub translations: Option<SourceFile>,
+}
+
+/// A variant of the CV, like `[variants.backend]`, that only keeps some of the entries with `tags`
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Variant {
+    /// The tagged entries are kept only if they have at least one of these tags, when some are given
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// The tagged entries having one of these tags are removed
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+impl Variant {
+    fn keeps(&self, entry: &serde_json::Value) -> bool {
+        let Some(tags) = entry.get(TAGS_KEY).and_then(serde_json::Value::as_array) else {
+            return true;
+        };
+
+        let has_tag = |tag: &String| tags.iter().any(|entry_tag| entry_tag.as_str() == Some(tag));
+
+        (self.include.is_empty() || self.include.iter().any(has_tag))
+            && !self.exclude.iter().any(has_tag)
+    }
+
+    /// Removes the entries of all the arrays, at any depth, that don't match the tag rules,
+    /// the entries without tags are always kept
+    pub fn filter(&self, data: &mut serde_json::Value) {
+        match data {
+            serde_json::Value::Array(entries) => {
+                entries.retain(|entry| self.keeps(entry));
+
+                for entry in entries {
+                    self.filter(entry);
+                }
+            }
+            serde_json::Value::Object(object) => {
+                for value in object.values_mut() {
+                    self.filter(value);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The `[lint]` section, like `[lint.rules]` with `unknown-filter = "warning"`
+#[derive(Debug, Default, Deserialize)]
+pub struct LintConfig {
+    #[serde(default)]
+    pub rules: HashMap<Rule, Severity>,
+}
+
+impl LintConfig {
+    /// The configured severity of the rule, or its default one
+    #[must_use]
+    pub fn severity(&self, rule: Rule) -> Severity {
+        self.rules
+            .get(&rule)
+            .copied()
+            .unwrap_or_else(|| rule.default_severity())
+    }
+}
+
+/// The `[export.json-resume]` section, the mapping table goes from the data paths, like `jobs[].company`,
+/// to the JSON Resume paths, like `work[].name`
+#[derive(Debug, Default, Deserialize)]
+pub struct JsonResumeExport {
+    /// Where the resume is written, `resume.json` next to the config file by default
+    pub path: Option<PathBuf>,
+    #[serde(default)]
+    pub mapping: BTreeMap<String, String>,
+}
+
+/// The `[export]` section
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ExportConfig {
+    #[serde(default)]
+    pub json_resume: JsonResumeExport,
 }
 
 #[derive(Debug, Deserialize)]
@@ -199,4 +445,146 @@ pub struct Config {
     pub output: Output,
     pub source: Source,
     pub template: Template,
+    #[serde(default)]
+    pub lint: LintConfig,
+    /// The languages, the CV is rendered once per language when there are some
+    #[serde(default)]
+    pub i18n: BTreeMap<String, Language>,
+    /// The variants, the CV is rendered once per variant (and per language) when there are some
+    #[serde(default)]
+    pub variants: BTreeMap<String, Variant>,
+    #[serde(default)]
+    pub export: ExportConfig,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(toml: &str) -> Vec<SourceFile> {
+        toml::from_str::<Source>(toml).unwrap().files
+    }
+
+    #[test]
+    fn finds_the_source_type_from_the_extension() {
+        let files =
+            files(r#"path = ["a.json", "resume.json", "b.yml", "c.yaml", "d.toml", "e.md"]"#);
+
+        assert!(matches!(
+            files.iter().map(|file| &file.type_).collect::<Vec<_>>()[..],
+            [
+                SourceType::Json,
+                SourceType::Json,
+                SourceType::Yaml,
+                SourceType::Yaml,
+                SourceType::Toml,
+                SourceType::Markdown,
+            ]
+        ));
+    }
+
+    #[test]
+    fn reads_the_explicit_source_type() {
+        let files = files(
+            r#"path = [{ path = "resume.json", type = "json-resume" }, { path = "data.txt", type = "yaml" }, { path = "f.toml" }]"#,
+        );
+
+        assert!(matches!(
+            files.iter().map(|file| &file.type_).collect::<Vec<_>>()[..],
+            [SourceType::JsonResume, SourceType::Yaml, SourceType::Toml]
+        ));
+        assert!(matches!(
+            &self::files(r#"path = { path = "cv.json", type = "json-resume" }"#)[..],
+            [SourceFile {
+                type_: SourceType::JsonResume,
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn rejects_unknown_source_types() {
+        assert!(toml::from_str::<Source>(r#"path = "cv.txt""#).is_err());
+        assert!(toml::from_str::<Source>(r#"path = { path = "cv.json", type = "xml" }"#).is_err());
+        assert!(toml::from_str::<Source>(r#"path = { path = "cv.json", kind = "json" }"#).is_err());
+    }
+
+    fn variant(toml: &str) -> Variant {
+        toml::from_str(toml).unwrap()
+    }
+
+    fn data() -> serde_json::Value {
+        serde_json::json!({
+            "name": "Jane",
+            "jobs": [
+                { "title": "A", "tags": ["backend"] },
+                { "title": "B", "tags": ["frontend", "old"] },
+                { "title": "C" },
+                {
+                    "title": "D",
+                    "tags": ["backend", "old"],
+                    "projects": [{ "name": "E", "tags": ["frontend"] }, { "name": "F", "tags": [] }],
+                },
+            ],
+            "skills": { "rust": [{ "name": "G", "tags": ["backend"] }] },
+        })
+    }
+
+    fn titles(data: &serde_json::Value) -> Vec<&str> {
+        data["jobs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|job| job["title"].as_str())
+            .collect()
+    }
+
+    #[test]
+    fn keeps_the_entries_with_an_included_tag() {
+        let mut data = data();
+
+        variant(r#"include = ["backend"]"#).filter(&mut data);
+
+        assert_eq!(titles(&data), ["A", "C", "D"]);
+        // The nested arrays are filtered too, an entry with no tags at all doesn't match
+        assert_eq!(data["jobs"][2]["projects"], serde_json::json!([]));
+        assert_eq!(data["skills"]["rust"][0]["name"], "G");
+    }
+
+    #[test]
+    fn removes_the_entries_with_an_excluded_tag() {
+        let mut data = data();
+
+        variant(r#"exclude = ["old"]"#).filter(&mut data);
+
+        assert_eq!(titles(&data), ["A", "C"]);
+
+        let mut data = self::data();
+
+        variant(
+            r#"include = ["backend", "frontend"]
+exclude = ["old"]"#,
+        )
+        .filter(&mut data);
+
+        assert_eq!(titles(&data), ["A", "C"]);
+    }
+
+    #[test]
+    fn keeps_everything_without_rules() {
+        let mut data = data();
+
+        Variant::default().filter(&mut data);
+
+        assert_eq!(data, self::data());
+    }
+
+    #[test]
+    fn rejects_unknown_variant_keys() {
+        let error = toml::from_str::<Variant>(r#"includes = ["backend"]"#).unwrap_err();
+
+        assert!(error
+            .to_string()
+            .contains("unknown field `includes`, expected `include` or `exclude`"));
+    }
 }
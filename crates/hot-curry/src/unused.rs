@@ -0,0 +1,190 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json::Value;
+
+use crate::{
+    schema::{key_path, parse_path, Segment},
+    visitor::TeraVariableVisitor,
+};
+
+#[derive(Debug, Default)]
+struct UsageNode {
+    properties: BTreeMap<String, UsageNode>,
+    items: Option<Box<UsageNode>>,
+    /// Set if the path of this node is used directly by the template
+    used: bool,
+    /// Set if the value is passed to a macro or an included template, which may read any descendant
+    used_whole: bool,
+}
+
+impl UsageNode {
+    fn node(&mut self, path: &str) -> &mut UsageNode {
+        parse_path(path)
+            .into_iter()
+            .fold(self, |node, segment| match segment {
+                Segment::Key(key) => node.properties.entry(key).or_default(),
+                Segment::Items => node.items.get_or_insert_with(Box::default),
+            })
+    }
+
+    /// A value used as a whole (printed, passed to a macro, used as a condition) uses all its descendants
+    fn uses_whole_value(&self) -> bool {
+        self.used_whole || (self.used && self.properties.is_empty() && self.items.is_none())
+    }
+
+    fn collect_unused(&self, value: &Value, path: &str, unused: &mut BTreeSet<String>) {
+        if self.uses_whole_value() {
+            return;
+        }
+
+        match value {
+            Value::Object(object) => {
+                for (key, value) in object {
+                    let key_path = key_path(path, key);
+
+                    // Objects can be iterated on with `{% for key, value in object %}`
+                    match self.properties.get(key).or(self.items.as_deref()) {
+                        Some(node) => node.collect_unused(value, &key_path, unused),
+                        None => {
+                            unused.insert(key_path);
+                        }
+                    }
+                }
+            }
+            Value::Array(items) => {
+                let items_path = format!("{path}[]");
+
+                match &self.items {
+                    Some(node) => {
+                        for item in items {
+                            node.collect_unused(item, &items_path, unused);
+                        }
+                    }
+                    None if !self.used && !items.is_empty() => {
+                        unused.insert(items_path);
+                    }
+                    None => {}
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Lists the paths of the data that are never read by the template, like `jobs[].company.address`,
+/// the items of arrays are merged under the `[]` notation
+#[must_use]
+pub fn find_unused_paths(visitor: &TeraVariableVisitor, data: &Value) -> Vec<String> {
+    let mut root = UsageNode::default();
+
+    for ident in visitor.idents() {
+        root.node(ident).used = true;
+    }
+
+    for ident in visitor.whole_idents() {
+        root.node(ident).used_whole = true;
+    }
+
+    let mut unused = BTreeSet::new();
+
+    root.collect_unused(data, "", &mut unused);
+
+    unused.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tera::Tera;
+
+    use super::*;
+
+    fn unused(templates: &[(&str, &str)], data: &Value) -> Vec<String> {
+        let mut tera = Tera::default();
+
+        tera.add_raw_templates(templates.to_vec()).unwrap();
+
+        let mut visitor = TeraVariableVisitor::with_tera(&tera);
+
+        visitor.visit_template(&tera.templates["main.html"]);
+
+        find_unused_paths(&visitor, data)
+    }
+
+    #[test]
+    fn values_passed_to_macros_are_used() {
+        let templates = [
+            (
+                "macros.html",
+                "{% macro job(j) %}{{ j.title }}{% endmacro job %}",
+            ),
+            (
+                "main.html",
+                "{% import 'macros.html' as m %}\
+                 {% for job in jobs %}{{ job.company }}{{ m::job(j=job) }}{% endfor %}",
+            ),
+        ];
+        let data = json!({ "jobs": [{ "company": "A", "title": "B" }], "name": "C" });
+
+        assert_eq!(unused(&templates, &data), vec!["name"]);
+    }
+
+    #[test]
+    fn loop_values_seen_by_included_templates_are_used() {
+        let templates = [
+            ("job.html", "{{ job.title }}"),
+            (
+                "main.html",
+                "{% for job in jobs %}{{ job.company }}{% include 'job.html' %}{% endfor %}",
+            ),
+        ];
+        let data = json!({ "jobs": [{ "company": "A", "title": "B", "city": "C" }] });
+
+        assert!(unused(&templates, &data).is_empty());
+    }
+
+    #[test]
+    fn lists_the_values_never_read() {
+        let templates = [(
+            "main.html",
+            "{{ name }}{{ address.city }}{% for job in jobs %}{{ job.title }}{% endfor %}",
+        )];
+        let data = json!({
+            "name": "A",
+            "email": "a@b.c",
+            "address": { "city": "B", "street": "C" },
+            "jobs": [{ "title": "D", "company": { "name": "E" } }, { "title": "F", "tags": ["x"] }],
+            "skills": ["G"],
+            "hobbies": [],
+        });
+
+        assert_eq!(
+            unused(&templates, &data),
+            vec![
+                "address.street",
+                "email",
+                "hobbies",
+                "jobs[].company",
+                "jobs[].tags",
+                "skills"
+            ]
+        );
+    }
+
+    #[test]
+    fn values_printed_whole_use_their_descendants() {
+        let templates = [(
+            "main.html",
+            "{{ address }}{% if links %}x{% endif %}{% for tag in tags %}{{ tag }}{% endfor %}\
+             {% for key, value in skills %}{{ value.level }}{% endfor %}",
+        )];
+        let data = json!({
+            "address": { "city": "A" },
+            "links": { "github": "B" },
+            "tags": [["C"]],
+            "skills": { "rust": { "level": 1, "since": 2 } },
+        });
+
+        assert_eq!(unused(&templates, &data), vec!["skills.rust.since"]);
+    }
+}
@@ -0,0 +1,457 @@
+use std::fmt::{self, Display, Formatter};
+
+use serde_json::{Map, Value};
+
+use crate::{diagnostics::Position, schema::key_path};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProblemKind {
+    /// A key required by the template is not in the data file
+    Missing,
+    /// A value used in a `for` loop can't be iterated on
+    NotIterable { found: &'static str },
+    /// The value doesn't have the type the template expects
+    TypeMismatch {
+        expected: String,
+        found: &'static str,
+    },
+    /// The key is not allowed by the schema
+    UnknownKey,
+    /// The string doesn't have the expected format, like a date
+    InvalidFormat { expected: &'static str },
+}
+
+/// A problem in the data file, `path` being the path of the value, like `jobs[2].title`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub path: String,
+    pub kind: ProblemKind,
+    /// The data file the value comes from, when known
+    pub file: Option<String>,
+    /// Where the value, or its closest ancestor when the value is missing, is written in the file
+    pub position: Option<Position>,
+}
+
+impl Problem {
+    #[must_use]
+    pub fn new(path: impl Into<String>, kind: ProblemKind) -> Self {
+        Self {
+            path: path.into(),
+            kind,
+            file: None,
+            position: None,
+        }
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{file}:")?;
+
+            if let Some(position) = self.position {
+                write!(f, "{position}:")?;
+            }
+
+            write!(f, " ")?;
+        }
+
+        match &self.kind {
+            ProblemKind::Missing => write!(f, "{}: missing required value", self.path),
+            ProblemKind::NotIterable { found } => {
+                write!(f, "{}: expected an array, but is {found}", self.path)
+            }
+            ProblemKind::TypeMismatch { expected, found } => {
+                write!(f, "{}: expected {expected}, but is {found}", self.path)
+            }
+            ProblemKind::UnknownKey => write!(f, "{}: unknown key", self.path),
+            ProblemKind::InvalidFormat { expected } => {
+                write!(f, "{}: expected {expected}", self.path)
+            }
+        }
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+/// Dates like `2023-01-31`, `2023-01`, or `2023`
+fn is_date(value: &str) -> bool {
+    let parts = value.split('-').collect::<Vec<_>>();
+
+    let lengths_match = matches!(
+        parts.iter().map(|part| part.len()).collect::<Vec<_>>()[..],
+        [4] | [4, 2] | [4, 2, 2]
+    );
+
+    lengths_match
+        && parts
+            .iter()
+            .all(|part| part.chars().all(|c| c.is_ascii_digit()))
+}
+
+fn is_email(value: &str) -> bool {
+    value.split_once('@').map_or(false, |(user, domain)| {
+        !user.is_empty() && domain.contains('.')
+    })
+}
+
+/// The supported `format` keywords, and what they expect
+fn check_format(format: &str, value: &str) -> Result<(), &'static str> {
+    match format {
+        "date" if !is_date(value) => Err("a date like 2023-01-31, 2023-01, or 2023"),
+        "email" if !is_email(value) => Err("an email address"),
+        _ => Ok(()),
+    }
+}
+
+fn validate_value(schema: &Value, value: &Value, path: &str, problems: &mut Vec<Problem>) {
+    let type_mismatch = |expected: &str| {
+        Problem::new(
+            path,
+            ProblemKind::TypeMismatch {
+                expected: expected.to_string(),
+                found: type_name(value),
+            },
+        )
+    };
+
+    match schema.get("type").and_then(Value::as_str) {
+        Some("array") => {
+            let items_schema = schema.get("items");
+
+            match value {
+                Value::Array(items) => {
+                    for (index, item) in items.iter().enumerate() {
+                        if let Some(items_schema) = items_schema {
+                            validate_value(
+                                items_schema,
+                                item,
+                                &format!("{path}[{index}]"),
+                                problems,
+                            );
+                        }
+                    }
+                }
+                // Objects can be iterated on with `{% for key, value in object %}`
+                Value::Object(object) => {
+                    for (key, item) in object {
+                        if let Some(items_schema) = items_schema {
+                            validate_value(items_schema, item, &key_path(path, key), problems);
+                        }
+                    }
+                }
+                _ => problems.push(Problem::new(
+                    path,
+                    ProblemKind::NotIterable {
+                        found: type_name(value),
+                    },
+                )),
+            }
+
+            return;
+        }
+        Some("object") if !value.is_object() => {
+            problems.push(type_mismatch("an object"));
+
+            return;
+        }
+        Some("string") if !value.is_string() => problems.push(type_mismatch("a string")),
+        Some("string") => {
+            let format = schema.get("format").and_then(Value::as_str);
+
+            if let (Some(format), Some(value)) = (format, value.as_str()) {
+                if let Err(expected) = check_format(format, value) {
+                    problems.push(Problem::new(path, ProblemKind::InvalidFormat { expected }));
+                }
+            }
+        }
+        Some("number") if !value.is_number() => problems.push(type_mismatch("a number")),
+        _ => {}
+    }
+
+    if let Some(object) = value.as_object() {
+        validate_object(schema, object, path, problems);
+    }
+}
+
+fn validate_object(
+    schema: &Value,
+    object: &Map<String, Value>,
+    path: &str,
+    problems: &mut Vec<Problem>,
+) {
+    let empty_properties = Map::new();
+    let properties = schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .unwrap_or(&empty_properties);
+
+    let required = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect::<Vec<_>>();
+
+    for &key in &required {
+        if !object.contains_key(key) {
+            problems.push(Problem::new(key_path(path, key), ProblemKind::Missing));
+        }
+    }
+
+    match schema.get("additionalProperties") {
+        Some(Value::Bool(false)) => {
+            for key in object.keys() {
+                if !properties.contains_key(key) {
+                    problems.push(Problem::new(key_path(path, key), ProblemKind::UnknownKey));
+                }
+            }
+        }
+        // The values of an object iterated with `{% for key, value in object %}`
+        Some(additional_schema @ Value::Object(_)) => {
+            for (key, property) in object {
+                if !properties.contains_key(key) {
+                    validate_value(additional_schema, property, &key_path(path, key), problems);
+                }
+            }
+        }
+        _ => {}
+    }
+
+    for (key, property_schema) in properties {
+        if let Some(property) = object.get(key) {
+            // An optional value set to null, like `jobs: ~`, is falsy for the template, like a missing one
+            if property.is_null() && !required.contains(&key.as_str()) {
+                continue;
+            }
+
+            validate_value(property_schema, property, &key_path(path, key), problems);
+        }
+    }
+}
+
+/// Checks the data against a JSON Schema, like the one inferred from the template, only the `type`, `properties`,
+/// `required`, `items`, `additionalProperties` (false or a schema), and `format` (`date` and `email`) keywords are supported
+#[must_use]
+pub fn validate(schema: &Value, data: &Value) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    validate_value(schema, data, "", &mut problems);
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "required": ["name", "jobs"],
+            "properties": {
+                "name": { "type": "string" },
+                "age": { "type": "number" },
+                "jobs": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "required": ["title"],
+                        "properties": { "title": { "type": "string" } },
+                    },
+                },
+            },
+        })
+    }
+
+    #[test]
+    fn accepts_matching_data() {
+        let data = json!({
+            "name": "Jane",
+            "age": 30,
+            "jobs": [{ "title": "A" }],
+            "extra": true,
+        });
+
+        assert_eq!(validate(&schema(), &data), vec![]);
+    }
+
+    #[test]
+    fn reports_missing_and_mistyped_values() {
+        let data = json!({ "age": "thirty", "jobs": [{ "title": 1 }, {}] });
+
+        assert_eq!(
+            validate(&schema(), &data),
+            vec![
+                Problem::new("name", ProblemKind::Missing),
+                Problem::new(
+                    "age",
+                    ProblemKind::TypeMismatch {
+                        expected: "a number".to_string(),
+                        found: "a string"
+                    }
+                ),
+                Problem::new(
+                    "jobs[0].title",
+                    ProblemKind::TypeMismatch {
+                        expected: "a string".to_string(),
+                        found: "a number"
+                    }
+                ),
+                Problem::new("jobs[1].title", ProblemKind::Missing),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_values_that_cant_be_iterated() {
+        let data = json!({ "name": "Jane", "jobs": "A" });
+
+        assert_eq!(
+            validate(&schema(), &data),
+            vec![Problem::new(
+                "jobs",
+                ProblemKind::NotIterable { found: "a string" }
+            )]
+        );
+    }
+
+    #[test]
+    fn accepts_null_optional_values() {
+        let mut schema = schema();
+
+        schema["properties"]["skills"] = json!({ "type": "array", "items": { "type": "string" } });
+
+        let data = json!({ "name": "Jane", "age": null, "jobs": [], "skills": null });
+
+        assert_eq!(validate(&schema, &data), vec![]);
+
+        let data = json!({ "name": null, "jobs": null });
+
+        assert_eq!(
+            validate(&schema, &data),
+            vec![
+                Problem::new("jobs", ProblemKind::NotIterable { found: "null" }),
+                Problem::new(
+                    "name",
+                    ProblemKind::TypeMismatch {
+                        expected: "a string".to_string(),
+                        found: "null"
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn iterates_on_objects() {
+        let data = json!({ "name": "Jane", "jobs": { "a": { "title": "A" }, "b": {} } });
+
+        assert_eq!(
+            validate(&schema(), &data),
+            vec![Problem::new("jobs.b.title", ProblemKind::Missing)]
+        );
+    }
+
+    #[test]
+    fn reports_invalid_formats() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "email": { "type": "string", "format": "email" },
+                "start": { "type": "string", "format": "date" },
+                "end": { "type": "string", "format": "date" },
+            },
+        });
+        let data = json!({ "email": "jane", "start": "01/2023", "end": "2023-01" });
+
+        assert_eq!(
+            validate(&schema, &data),
+            vec![
+                Problem::new(
+                    "email",
+                    ProblemKind::InvalidFormat {
+                        expected: "an email address"
+                    }
+                ),
+                Problem::new(
+                    "start",
+                    ProblemKind::InvalidFormat {
+                        expected: "a date like 2023-01-31, 2023-01, or 2023"
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_unknown_keys() {
+        let schema = json!({
+            "type": "object",
+            "properties": { "name": {} },
+            "additionalProperties": false,
+        });
+
+        assert_eq!(
+            validate(&schema, &json!({ "name": "Jane", "extra": 1 })),
+            vec![Problem::new("extra", ProblemKind::UnknownKey)]
+        );
+    }
+
+    #[test]
+    fn validates_the_values_of_objects_iterated_with_a_key() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "links": {
+                    "type": "object",
+                    "additionalProperties": { "type": "string" },
+                },
+            },
+        });
+
+        assert_eq!(
+            validate(&schema, &json!({ "links": { "site": "a", "phone": 1 } })),
+            vec![Problem::new(
+                "links.phone",
+                ProblemKind::TypeMismatch {
+                    expected: "a string".to_string(),
+                    found: "a number"
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn displays_the_file_and_position() {
+        let mut problem = Problem::new("jobs[1].title", ProblemKind::Missing);
+
+        assert_eq!(problem.to_string(), "jobs[1].title: missing required value");
+
+        problem.file = Some("jobs.yml".to_string());
+
+        assert_eq!(
+            problem.to_string(),
+            "jobs.yml: jobs[1].title: missing required value"
+        );
+
+        problem.position = Some(Position::new(3, 5));
+
+        assert_eq!(
+            problem.to_string(),
+            "jobs.yml:3:5: jobs[1].title: missing required value"
+        );
+    }
+}
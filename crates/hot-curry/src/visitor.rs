@@ -1,24 +1,254 @@
-// TODO: Add a "validation" visitor, that will checks that no include are used in templates, or if it's not too complex, allow for multiple files
-
 use std::collections::HashSet;
 
-use tera::ast::{Expr, ExprVal, WS};
-use tera_visitor::VisitorMut as TeraVisitorMut;
+use tera::{
+    ast::{
+        Block, Expr, ExprVal, FilterSection, Forloop, LogicExpr, LogicOperator, MacroCall, Set,
+        Test, WS,
+    },
+    Template, Tera,
+};
+use tera_visitor::{Ancestry, VisitorMut as TeraVisitorMut};
+
+/// Filters that only accept strings
+const STRING_FILTERS: &[&str] = &[
+    "addslashes",
+    "capitalize",
+    "escape",
+    "escape_xml",
+    "indent",
+    "linebreaksbr",
+    "lower",
+    "markdown",
+    "replace",
+    "slugify",
+    "spaceless",
+    "split",
+    "striptags",
+    "title",
+    "trim",
+    "trim_end",
+    "trim_end_matches",
+    "trim_start",
+    "trim_start_matches",
+    "truncate",
+    "upper",
+    "urlencode",
+    "urlencode_strict",
+    "wordcount",
+];
+
+/// Filters that only accept numbers
+const NUMBER_FILTERS: &[&str] = &["abs", "filesizeformat", "round"];
+
+#[derive(Debug)]
+struct ForloopScope {
+    key: Option<String>,
+    value: String,
+    /// The data path of the items, `None` if the container is not some data, like `range(end=5)`
+    item_path: Option<String>,
+    /// The variables set in the loop body
+    locals: HashSet<String>,
+}
+
+/// Splits `job.title` and `job["title"]` in `job` and the attribute access
+fn split_ident(ident: &str) -> (&str, &str) {
+    ident.split_at(ident.find(['.', '[']).unwrap_or(ident.len()))
+}
+
+/// The variables bound by the template itself, by the loops and the `set` tags, to tell them apart from the data
+#[derive(Debug, Default)]
+pub(crate) struct Scopes {
+    forloops: Vec<ForloopScope>,
+    global_locals: HashSet<String>,
+}
+
+impl Scopes {
+    /// The loops whose body is being visited, the loop currently visited is not active yet
+    /// when its container is visited
+    fn active_forloops(&self, ancestry: &Ancestry) -> &[ForloopScope] {
+        &self.forloops[..ancestry.forloop_bindings().count()]
+    }
+
+    /// Maps an ident to the data path it reads, so `job.title` in `{% for job in jobs %}` is `jobs[].title`,
+    /// returns `None` for local variables (loop bindings, `loop`, `set` variables, and macro arguments)
+    pub(crate) fn resolve_ident(&self, ancestry: &Ancestry, ident: &str) -> Option<String> {
+        // Macros can't read the template context, only their arguments
+        if ancestry.macro_definition_name().is_some() {
+            return None;
+        }
+
+        let (root, attributes) = split_ident(ident);
 
+        let active_forloops = self.active_forloops(ancestry);
+
+        if root == "loop" && !active_forloops.is_empty() {
+            return None;
+        }
+
+        for forloop in active_forloops.iter().rev() {
+            if forloop.locals.contains(root) || forloop.key.as_deref() == Some(root) {
+                return None;
+            }
+
+            if forloop.value == root {
+                return forloop
+                    .item_path
+                    .as_ref()
+                    .map(|item_path| format!("{item_path}{attributes}"));
+            }
+        }
+
+        if self.global_locals.contains(root) {
+            return None;
+        }
+
+        Some(ident.to_string())
+    }
+
+    /// The data paths of the items of the active loops
+    pub(crate) fn item_paths<'s>(&'s self, ancestry: &Ancestry) -> impl Iterator<Item = &'s str> {
+        self.active_forloops(ancestry)
+            .iter()
+            .filter_map(|forloop| forloop.item_path.as_deref())
+    }
+
+    /// Called before visiting a loop, `container_path` being the data path of the container, if it's some data
+    pub(crate) fn push_forloop(&mut self, forloop: &Forloop, container_path: Option<&str>) {
+        self.forloops.push(ForloopScope {
+            key: forloop.key.clone(),
+            value: forloop.value.clone(),
+            item_path: container_path.map(|path| format!("{path}[]")),
+            locals: HashSet::new(),
+        });
+    }
+
+    pub(crate) fn pop_forloop(&mut self) {
+        self.forloops.pop();
+    }
+
+    pub(crate) fn insert_set(&mut self, ancestry: &Ancestry, set: &Set) {
+        // A `set` in a loop body only lives until the end of the iteration
+        match self.forloops[..ancestry.forloop_bindings().count()].last_mut() {
+            Some(forloop) if !set.global => {
+                forloop.locals.insert(set.key.clone());
+            }
+            _ => {
+                self.global_locals.insert(set.key.clone());
+            }
+        }
+    }
+}
+
+/// The block being rendered, `level` being the index of its definition in the inheritance chain
+#[derive(Debug)]
+struct BlockLevel {
+    name: String,
+    level: usize,
+}
+
+/// Visits a template and, when the `Tera` instance is provided, the templates it extends, includes, and imports
 #[allow(clippy::module_name_repetitions)]
 #[derive(Debug, Default)]
-pub struct TeraVariableVisitor {
+pub struct TeraVariableVisitor<'a> {
+    tera: Option<&'a Tera>,
+    /// The inheritance chains of the templates being visited, each chain starting with the most derived template
+    inheritance_chains: Vec<Vec<&'a Template>>,
+    block_levels: Vec<BlockLevel>,
+    /// The parent, included, and imported templates already visited, each template is visited only once
+    visited_templates: HashSet<String>,
     idents: HashSet<String>,
     iterable_idents: HashSet<String>,
-    optional_idents: HashSet<String>,
+    keyed_iterable_idents: HashSet<String>,
+    whole_idents: HashSet<String>,
+    /// Idents used as a condition, tested with `is defined`, or with a `default` value
+    guarded_idents: HashSet<String>,
+    unconditional_idents: HashSet<String>,
+    string_idents: HashSet<String>,
+    number_idents: HashSet<String>,
+    filters: HashSet<String>,
+    includes: HashSet<String>,
+    macro_calls: HashSet<String>,
+    ancestry: Ancestry,
+    scopes: Scopes,
 }
 
-impl TeraVariableVisitor {
+impl<'a> TeraVariableVisitor<'a> {
     #[must_use]
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// A visitor that follows `extends`, `include`, and `import` using the templates of `tera`
+    #[must_use]
+    pub fn with_tera(tera: &'a Tera) -> Self {
+        Self {
+            tera: Some(tera),
+            ..Self::default()
+        }
+    }
+
+    /// Visits a template, starting from the top of its inheritance chain
+    /// so the blocks are visited where they're rendered, with their most derived definition
+    pub fn visit_template(&mut self, template: &'a Template) {
+        let mut inheritance_chain = vec![template];
+
+        if let Some(tera) = self.tera {
+            while let Some(parent) = inheritance_chain
+                .last()
+                .and_then(|template| template.parent.as_ref())
+                .and_then(|parent| tera.templates.get(parent))
+            {
+                // Tera rejects circular extends, but the main template is not registered in the `Tera` instance
+                if inheritance_chain
+                    .iter()
+                    .any(|template| template.name == parent.name)
+                {
+                    break;
+                }
+
+                self.visited_templates.insert(parent.name.clone());
+
+                inheritance_chain.push(parent);
+            }
+        }
+
+        // The top level of the derived templates is not rendered, but their imports are used by their blocks
+        for derived_template in &inheritance_chain[..inheritance_chain.len() - 1] {
+            for (path, _) in &derived_template.imported_macro_files {
+                self.visit_template_once(path);
+            }
+        }
+
+        let root_template = inheritance_chain[inheritance_chain.len() - 1];
+
+        self.inheritance_chains.push(inheritance_chain);
+
+        self.visit_ast_mut(&root_template.ast);
+
+        self.inheritance_chains.pop();
+    }
+
+    /// Visits a template registered in the `Tera` instance if it hasn't been visited yet
+    fn visit_template_once(&mut self, name: &str) {
+        let Some(template) = self.tera.and_then(|tera| tera.templates.get(name)) else {
+            return;
+        };
+
+        if self.visited_templates.insert(name.to_string()) {
+            self.visit_template(template);
+        }
+    }
+
+    /// The definitions of a block in the current inheritance chain, the most derived one first
+    fn block_definitions(&self, name: &str) -> Vec<&'a Block> {
+        self.inheritance_chains
+            .last()
+            .into_iter()
+            .flatten()
+            .filter_map(|template| template.blocks.get(name))
+            .collect()
+    }
+
     #[must_use]
     pub fn idents(&self) -> &HashSet<String> {
         &self.idents
@@ -49,57 +279,518 @@ impl TeraVariableVisitor {
         iterable_idents
     }
 
+    /// Iterable idents looped over with a key, like `skills` in `{% for name, level in skills %}`,
+    /// which must be objects
     #[must_use]
-    pub fn optional_idents(&self) -> &HashSet<String> {
-        &self.optional_idents
+    pub fn keyed_iterable_idents(&self) -> &HashSet<String> {
+        &self.keyed_iterable_idents
     }
 
-    pub fn sorted_optional_idents(&mut self) -> Vec<String> {
-        let mut optional_idents = self
-            .optional_idents()
+    /// Idents passed to a macro, or to an included template as a loop variable, the reads of their
+    /// attributes in the macro or the included template are not followed, so they're used as a whole
+    #[must_use]
+    pub fn whole_idents(&self) -> &HashSet<String> {
+        &self.whole_idents
+    }
+
+    /// Idents used as a condition, tested with `is defined` (or `is undefined`), given a `default` value,
+    /// or only used inside an `if` branch (or the `else` of a `for`)
+    #[must_use]
+    pub fn optional_idents(&self) -> HashSet<String> {
+        self.idents
             .iter()
+            .filter(|ident| {
+                self.guarded_idents.contains(*ident) || !self.unconditional_idents.contains(*ident)
+            })
             .map(Clone::clone)
-            .collect::<Vec<_>>();
+            .collect()
+    }
+
+    pub fn sorted_optional_idents(&mut self) -> Vec<String> {
+        let mut optional_idents = self.optional_idents().into_iter().collect::<Vec<_>>();
 
         optional_idents.sort();
 
         optional_idents
     }
+
+    /// Idents that must be strings, as they go through a string filter like `upper` or `markdown`
+    #[must_use]
+    pub fn string_idents(&self) -> &HashSet<String> {
+        &self.string_idents
+    }
+
+    /// Idents that must be numbers, as they go through a number filter like `round` or are used in a math expression
+    #[must_use]
+    pub fn number_idents(&self) -> &HashSet<String> {
+        &self.number_idents
+    }
+
+    /// The templates visited through `extends`, `include`, and `import`
+    #[must_use]
+    pub fn visited_templates(&self) -> &HashSet<String> {
+        &self.visited_templates
+    }
+
+    /// The filters applied in the template, including the ones of `{% filter %}` sections
+    #[must_use]
+    pub fn filters(&self) -> &HashSet<String> {
+        &self.filters
+    }
+
+    /// The templates included with `{% include %}`
+    #[must_use]
+    pub fn includes(&self) -> &HashSet<String> {
+        &self.includes
+    }
+
+    /// The macros called in the template, like `macros::header`
+    #[must_use]
+    pub fn macro_calls(&self) -> &HashSet<String> {
+        &self.macro_calls
+    }
+
+    fn resolve_ident(&self, ident: &str) -> Option<String> {
+        self.scopes.resolve_ident(&self.ancestry, ident)
+    }
+
+    fn insert_number_ident(&mut self, expr: &Expr) {
+        if let ExprVal::Ident(ident) = &expr.val {
+            if expr.filters.is_empty() {
+                if let Some(path) = self.resolve_ident(ident) {
+                    self.number_idents.insert(path);
+                }
+            }
+        }
+    }
+
+    /// Idents used as a condition, directly or as an operand of `and`/`or`, are potentially optional
+    fn insert_condition_idents(&mut self, expr: &Expr) {
+        match &expr.val {
+            ExprVal::Ident(ident) => {
+                if let Some(path) = self.resolve_ident(ident) {
+                    self.guarded_idents.insert(path);
+                }
+            }
+            ExprVal::Logic(LogicExpr {
+                lhs,
+                rhs,
+                operator: LogicOperator::And | LogicOperator::Or,
+            }) => {
+                self.insert_condition_idents(lhs);
+                self.insert_condition_idents(rhs);
+            }
+            _ => {}
+        }
+    }
 }
 
-impl TeraVisitorMut for TeraVariableVisitor {
-    fn visit_variable_block_mut(&mut self, ws: &WS, expr: &Expr) {
+impl TeraVisitorMut for TeraVariableVisitor<'_> {
+    fn ancestry_mut(&mut self) -> Option<&mut Ancestry> {
+        Some(&mut self.ancestry)
+    }
+
+    fn visit_ident_expr_val_mut(&mut self, ident: &str) {
+        if let Some(path) = self.resolve_ident(ident) {
+            if !self.ancestry.is_conditional() {
+                self.unconditional_idents.insert(path.clone());
+            }
+
+            self.idents.insert(path);
+        }
+
+        tera_visitor::visit_ident_expr_val_mut(self, ident);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &Expr) {
+        // Like Tera, which only falls back to the default value if `default` is the first filter
         if let ExprVal::Ident(ident) = &expr.val {
-            self.idents.insert(ident.to_string());
+            if expr.has_default_filter() {
+                if let Some(path) = self.resolve_ident(ident) {
+                    self.guarded_idents.insert(path);
+                }
+            }
+        }
+
+        if let (ExprVal::Ident(ident), Some(filter)) = (&expr.val, expr.filters.first()) {
+            if let Some(path) = self.resolve_ident(ident) {
+                if STRING_FILTERS.contains(&filter.name.as_str()) {
+                    self.string_idents.insert(path);
+                } else if NUMBER_FILTERS.contains(&filter.name.as_str()) {
+                    self.number_idents.insert(path);
+                }
+            }
+        }
+
+        self.filters
+            .extend(expr.filters.iter().map(|filter| filter.name.clone()));
+
+        tera_visitor::visit_expr_mut(self, expr);
+    }
+
+    fn visit_filter_section_mut(
+        &mut self,
+        left_ws: &WS,
+        filter_section: &FilterSection,
+        right_ws: &WS,
+    ) {
+        self.filters.insert(filter_section.filter.name.clone());
+
+        tera_visitor::visit_filter_section_mut(self, left_ws, filter_section, right_ws);
+    }
+
+    fn visit_include_mut(&mut self, ws: &WS, includes: &[String], ignore_missing: bool) {
+        self.includes.extend(includes.iter().cloned());
+
+        self.whole_idents.extend(
+            self.scopes
+                .item_paths(&self.ancestry)
+                .map(ToString::to_string),
+        );
+
+        // Tera renders the first template of the list that exists
+        if let Some(include) = includes.iter().find(|include| {
+            self.tera
+                .map_or(false, |tera| tera.templates.contains_key(*include))
+        }) {
+            self.visit_template_once(include);
         }
 
-        tera_visitor::visit_variable_block_mut(self, ws, expr);
+        tera_visitor::visit_include_mut(self, ws, includes, ignore_missing);
+    }
+
+    fn visit_import_macro_mut(&mut self, ws: &WS, path: &str, name: &str) {
+        self.visit_template_once(path);
+
+        tera_visitor::visit_import_macro_mut(self, ws, path, name);
+    }
+
+    fn visit_block_mut(&mut self, left_ws: &WS, block: &Block, right_ws: &WS) {
+        let block = self
+            .block_definitions(&block.name)
+            .first()
+            .copied()
+            .unwrap_or(block);
+
+        self.block_levels.push(BlockLevel {
+            name: block.name.clone(),
+            level: 0,
+        });
+
+        tera_visitor::visit_block_mut(self, left_ws, block, right_ws);
+
+        self.block_levels.pop();
+    }
+
+    fn visit_super_mut(&mut self) {
+        if let Some(BlockLevel { name, level }) = self.block_levels.last() {
+            let (name, level) = (name.clone(), level + 1);
+
+            if let Some(block) = self.block_definitions(&name).get(level).copied() {
+                self.block_levels.push(BlockLevel { name, level });
+
+                self.visit_ast_mut(&block.body);
+
+                self.block_levels.pop();
+            }
+        }
+
+        tera_visitor::visit_super_mut(self);
+    }
+
+    fn visit_macro_call_mut(&mut self, macro_call: &MacroCall) {
+        self.macro_calls
+            .insert(format!("{}::{}", macro_call.namespace, macro_call.name));
+
+        for arg in macro_call.args.values() {
+            if let ExprVal::Ident(ident) = &arg.val {
+                if let Some(path) = self.resolve_ident(ident) {
+                    self.whole_idents.insert(path);
+                }
+            }
+        }
+
+        tera_visitor::visit_macro_call_mut(self, macro_call);
+    }
+
+    fn visit_math_lhs_expr_mut(&mut self, expr: &Expr) {
+        self.insert_number_ident(expr);
+
+        tera_visitor::visit_math_lhs_expr_mut(self, expr);
+    }
+
+    fn visit_math_rhs_expr_mut(&mut self, expr: &Expr) {
+        self.insert_number_ident(expr);
+
+        tera_visitor::visit_math_rhs_expr_mut(self, expr);
+    }
+
+    fn visit_set_mut(&mut self, ws: &WS, set: &Set) {
+        tera_visitor::visit_set_mut(self, ws, set);
+
+        self.scopes.insert_set(&self.ancestry, set);
+    }
+
+    fn visit_forloop_mut(&mut self, left_ws: &WS, forloop: &Forloop, right_ws: &WS) {
+        let container_path = match &forloop.container.val {
+            ExprVal::Ident(ident) => self.resolve_ident(ident),
+            _ => None,
+        };
+
+        if let (Some(path), Some(_)) = (&container_path, &forloop.key) {
+            self.keyed_iterable_idents.insert(path.clone());
+        }
+
+        self.scopes.push_forloop(forloop, container_path.as_deref());
+
+        tera_visitor::visit_forloop_mut(self, left_ws, forloop, right_ws);
+
+        self.scopes.pop_forloop();
     }
 
     fn visit_forloop_expr_mut(&mut self, expr: &Expr) {
         if let ExprVal::Ident(ident) = &expr.val {
-            self.idents.insert(ident.to_string());
-            self.iterable_idents.insert(ident.to_string());
+            if let Some(path) = self.resolve_ident(ident) {
+                self.iterable_idents.insert(path);
+            }
         }
 
         tera_visitor::visit_forloop_expr_mut(self, expr);
     }
 
-    fn visit_if_expr_mut(&mut self, expr: &Expr) {
-        if let ExprVal::Ident(ident) = &expr.val {
-            self.idents.insert(ident.to_string());
-            self.optional_idents.insert(ident.to_string());
+    fn visit_test_expr_val_mut(&mut self, test: &Test) {
+        if ["defined", "undefined"].contains(&test.name.as_str()) {
+            if let Some(path) = self.resolve_ident(&test.ident) {
+                self.guarded_idents.insert(path);
+            }
         }
 
+        tera_visitor::visit_test_expr_val_mut(self, test);
+    }
+
+    fn visit_if_expr_mut(&mut self, expr: &Expr) {
+        self.insert_condition_idents(expr);
+
         tera_visitor::visit_if_expr_mut(self, expr);
     }
 
     fn visit_else_if_expr_mut(&mut self, expr: &Expr) {
-        if let ExprVal::Ident(ident) = &expr.val {
-            self.idents.insert(ident.to_string());
-            self.optional_idents.insert(ident.to_string());
-        }
+        self.insert_condition_idents(expr);
 
         tera_visitor::visit_else_if_expr_mut(self, expr);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn visit(source: &str) -> TeraVariableVisitor<'static> {
+        let template = Template::new("", None, source).unwrap();
+        let mut visitor = TeraVariableVisitor::new();
+
+        visitor.visit_ast_mut(&template.ast);
+
+        visitor
+    }
+
+    fn idents(source: &str) -> Vec<String> {
+        visit(source).sorted_idents()
+    }
+
+    #[test]
+    fn skips_the_set_variables() {
+        assert_eq!(
+            idents(r#"{% set full_name = name ~ " " ~ surname %}{{ full_name }}"#),
+            ["name", "surname"]
+        );
+        // `set_global` in a loop sets a variable visible after the loop
+        assert_eq!(
+            idents("{% for job in jobs %}{% set_global last = job.title %}{% endfor %}{{ last }}"),
+            ["jobs", "jobs[].title"]
+        );
+    }
+
+    #[test]
+    fn scopes_the_set_variables_to_the_loop_body() {
+        assert_eq!(
+            idents(
+                "{% for job in jobs %}{% set title = job.title | upper %}{{ title }}{% endfor %}{{ title }}"
+            ),
+            ["jobs", "jobs[].title", "title"]
+        );
+    }
+
+    #[test]
+    fn maps_the_loop_bindings_to_the_items() {
+        assert_eq!(
+            idents(
+                "{% for job in jobs %}{% for tag in job.tags %}{{ tag.name }}{% endfor %}{% endfor %}"
+            ),
+            ["jobs", "jobs[].tags", "jobs[].tags[].name"]
+        );
+        // The key is a local, the value is the item
+        assert_eq!(
+            idents("{% for name, level in languages %}{{ name }}{{ level.label }}{% endfor %}"),
+            ["languages", "languages[].label"]
+        );
+        // The loop items of a container that's not data are locals too
+        assert!(idents("{% for i in range(end=3) %}{{ i }}{% endfor %}").is_empty());
+    }
+
+    #[test]
+    fn skips_the_loop_variable_inside_loops() {
+        assert_eq!(
+            idents("{% for job in jobs %}{{ loop.index }}{{ loop.last }}{% endfor %}{{ loop }}"),
+            ["jobs", "loop"]
+        );
+    }
+
+    #[test]
+    fn skips_the_macro_parameters() {
+        assert_eq!(
+            idents(
+                "{% macro job(title, company='') %}{{ title }}{{ company }}{% endmacro %}\
+                 {{ self::job(title=role) }}"
+            ),
+            ["role"]
+        );
+    }
+
+    #[test]
+    fn guarded_values_are_optional() {
+        let mut visitor = visit(
+            "{{ name }}{{ nickname | default(value=name) }}{{ title | upper | default(value='') }}\
+             {% if photo is defined %}{% endif %}{{ photo }}{% if email %}{% endif %}{{ email }}",
+        );
+
+        // `default` is only applied to missing values when it's the first filter
+        assert_eq!(
+            visitor.sorted_optional_idents(),
+            ["email", "nickname", "photo"]
+        );
+    }
+
+    /// The data paths read by `source`, and the templates visited, with the `templates` registered in Tera
+    fn analyze(templates: &[(&str, &str)], source: &str) -> (Vec<String>, Vec<String>) {
+        let mut tera = Tera::default();
+        tera.add_raw_templates(templates.to_vec()).unwrap();
+
+        let template = Template::new("", None, source).unwrap();
+        let mut visitor = TeraVariableVisitor::with_tera(&tera);
+
+        visitor.visit_template(&template);
+
+        let mut visited_templates = visitor
+            .visited_templates()
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        visited_templates.sort();
+
+        (visitor.sorted_idents(), visited_templates)
+    }
+
+    const BASE: (&str, &str) = (
+        "base.html",
+        "{{ name }}{% block main %}{{ summary }}{% endblock %}{% block footer %}{{ email }}{% endblock %}",
+    );
+
+    #[test]
+    fn visits_the_block_overrides() {
+        assert_eq!(
+            analyze(
+                &[BASE],
+                r#"{% extends "base.html" %}{{ ignored }}{% block main %}{{ jobs }}{% endblock %}"#
+            ),
+            (
+                vec!["email".to_string(), "jobs".to_string(), "name".to_string()],
+                vec!["base.html".to_string()]
+            )
+        );
+    }
+
+    #[test]
+    fn visits_the_parent_blocks_with_super() {
+        let middle = (
+            "middle.html",
+            r#"{% extends "base.html" %}{% block main %}{{ skills }}{{ super() }}{% endblock %}"#,
+        );
+
+        assert_eq!(
+            analyze(
+                &[BASE, middle],
+                r#"{% extends "middle.html" %}{% block main %}{{ jobs }}{{ super() }}{% endblock %}"#
+            ),
+            (
+                vec![
+                    "email".to_string(),
+                    "jobs".to_string(),
+                    "name".to_string(),
+                    "skills".to_string(),
+                    "summary".to_string(),
+                ],
+                vec!["base.html".to_string(), "middle.html".to_string()]
+            )
+        );
+    }
+
+    #[test]
+    fn visits_the_included_templates() {
+        let job = ("job.html", "{{ job.title }}{{ company }}");
+
+        assert_eq!(
+            analyze(
+                &[job],
+                r#"{% for job in jobs %}{% include ["missing.html", "job.html"] %}{% endfor %}"#
+            ),
+            (
+                vec![
+                    "company".to_string(),
+                    "jobs".to_string(),
+                    "jobs[].title".to_string(),
+                ],
+                vec!["job.html".to_string()]
+            )
+        );
+    }
+
+    #[test]
+    fn visits_the_imported_macros() {
+        let icons = (
+            "icons.html",
+            "{% macro icon(name) %}{{ name }}{% endmacro %}",
+        );
+        let macros = (
+            "macros.html",
+            r#"{% import "icons.html" as icons %}
+               {% macro link(url) %}<a href="{{ url }}">{{ icons::icon(name=site) }}</a>{% endmacro %}"#,
+        );
+
+        // The macros only read their arguments, not the data
+        assert_eq!(
+            analyze(
+                &[icons, macros],
+                r#"{% import "macros.html" as m %}{{ m::link(url=website) }}"#
+            ),
+            (
+                vec!["website".to_string()],
+                vec!["icons.html".to_string(), "macros.html".to_string()]
+            )
+        );
+    }
+
+    #[test]
+    fn visits_the_templates_once() {
+        let recursive = (
+            "recursive.html",
+            r#"{{ name }}{% include "recursive.html" %}"#,
+        );
+
+        assert_eq!(
+            analyze(&[recursive], r#"{% include "recursive.html" %}"#),
+            (vec!["name".to_string()], vec!["recursive.html".to_string()])
+        );
+    }
+}
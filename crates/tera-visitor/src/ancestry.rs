@@ -0,0 +1,127 @@
+use std::fmt::{self, Display, Formatter};
+
+/// A node containing the one being visited
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ancestor {
+    /// The body of a `{% for key, value in items %}`
+    Forloop { key: Option<String>, value: String },
+    /// The `{% else %}` body of a `{% for %}`, rendered when the container is empty
+    ForloopEmpty,
+    /// The condition and body of an `{% if %}` (branch 0) or of an `{% elif %}` (branch 1 and up)
+    If { branch: usize },
+    /// The `{% else %}` body of an `{% if %}`
+    IfOtherwise,
+    /// The body and the default arguments of a `{% macro name() %}`
+    MacroDefinition { name: String },
+    /// The body of a `{% block name %}`
+    Block { name: String },
+    /// The body of a `{% filter name %}`
+    FilterSection { name: String },
+}
+
+impl Display for Ancestor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Ancestor::Forloop {
+                key: Some(key),
+                value,
+            } => write!(f, "for {key}, {value}"),
+            Ancestor::Forloop { key: None, value } => write!(f, "for {value}"),
+            Ancestor::ForloopEmpty => write!(f, "for else"),
+            Ancestor::If { branch: 0 } => write!(f, "if"),
+            Ancestor::If { branch } => write!(f, "elif #{branch}"),
+            Ancestor::IfOtherwise => write!(f, "else"),
+            Ancestor::MacroDefinition { name } => write!(f, "macro {name}"),
+            Ancestor::Block { name } => write!(f, "block {name}"),
+            Ancestor::FilterSection { name } => write!(f, "filter {name}"),
+        }
+    }
+}
+
+/// The stack of ancestors of the node being visited, the closest ancestor being the last one
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Ancestry {
+    ancestors: Vec<Ancestor>,
+}
+
+impl Ancestry {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, ancestor: Ancestor) {
+        self.ancestors.push(ancestor);
+    }
+
+    pub fn pop(&mut self) -> Option<Ancestor> {
+        self.ancestors.pop()
+    }
+
+    #[must_use]
+    pub fn ancestors(&self) -> &[Ancestor] {
+        &self.ancestors
+    }
+
+    /// The closest ancestor
+    #[must_use]
+    pub fn parent(&self) -> Option<&Ancestor> {
+        self.ancestors.last()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.ancestors.is_empty()
+    }
+
+    /// The node is only rendered if some condition holds: in an `if`/`elif`/`else`, or in the `else` of a `for`
+    #[must_use]
+    pub fn is_conditional(&self) -> bool {
+        self.ancestors.iter().any(|ancestor| {
+            matches!(
+                ancestor,
+                Ancestor::If { .. } | Ancestor::IfOtherwise | Ancestor::ForloopEmpty
+            )
+        })
+    }
+
+    /// The node is in the body of a `for`, `break` and `continue` are allowed
+    #[must_use]
+    pub fn is_in_forloop(&self) -> bool {
+        self.ancestors
+            .iter()
+            .any(|ancestor| matches!(ancestor, Ancestor::Forloop { .. }))
+    }
+
+    /// The `(key, value)` names of the enclosing loops, the innermost loop being the last one
+    pub fn forloop_bindings(&self) -> impl Iterator<Item = (Option<&str>, &str)> {
+        self.ancestors.iter().filter_map(|ancestor| match ancestor {
+            Ancestor::Forloop { key, value } => Some((key.as_deref(), value.as_str())),
+            _ => None,
+        })
+    }
+
+    /// The name of the enclosing macro definition, if any
+    #[must_use]
+    pub fn macro_definition_name(&self) -> Option<&str> {
+        self.ancestors
+            .iter()
+            .rev()
+            .find_map(|ancestor| match ancestor {
+                Ancestor::MacroDefinition { name } => Some(name.as_str()),
+                _ => None,
+            })
+    }
+
+    /// The name of the innermost enclosing block, if any
+    #[must_use]
+    pub fn block_name(&self) -> Option<&str> {
+        self.ancestors
+            .iter()
+            .rev()
+            .find_map(|ancestor| match ancestor {
+                Ancestor::Block { name } => Some(name.as_str()),
+                _ => None,
+            })
+    }
+}
@@ -0,0 +1,852 @@
+#![allow(clippy::module_name_repetitions)]
+
+use tera::ast::{
+    Block, Expr, ExprVal, FilterSection, Forloop, FunctionCall, If, In, LogicExpr, MacroCall,
+    MacroDefinition, MathExpr, Node, Set, StringConcat, Test, WS,
+};
+
+use crate::{Ancestor, Ancestry, Ast};
+
+/// Takes the AST by value and returns the rewritten one
+pub trait Fold {
+    /// The ancestors of the folded node, kept up to date by the walkers if some storage is returned
+    fn ancestry_mut(&mut self) -> Option<&mut Ancestry> {
+        None
+    }
+
+    // Whole AST
+    fn fold_ast(&mut self, ast: Ast) -> Ast {
+        fold_ast(self, ast)
+    }
+
+    /// Any node, returning several nodes (or none) allows for wrapping (or stripping) nodes
+    fn fold_node(&mut self, node: Node) -> Ast {
+        fold_node(self, node)
+    }
+
+    /// A call to `{{ super() }}` in a block
+    fn fold_super(&mut self) -> Node {
+        fold_super(self)
+    }
+
+    /// Some actual text
+    fn fold_text(&mut self, text: String) -> Node {
+        fold_text(self, text)
+    }
+
+    /// A `{{ }}` block
+    fn fold_variable_block(&mut self, ws: WS, expr: Expr) -> Node {
+        fold_variable_block(self, ws, expr)
+    }
+
+    /// A `{% macro hello() %}...{% endmacro %}`
+    fn fold_macro_definition(
+        &mut self,
+        left_ws: WS,
+        macro_definition: MacroDefinition,
+        right_ws: WS,
+    ) -> Node {
+        fold_macro_definition(self, left_ws, macro_definition, right_ws)
+    }
+
+    /// The `{% extends "blabla.html" %}` node, contains the template name
+    fn fold_extends(&mut self, ws: WS, content: String) -> Node {
+        fold_extends(self, ws, content)
+    }
+
+    /// The `{% include "blabla.html" %}` node, contains the template name
+    fn fold_include(&mut self, ws: WS, includes: Vec<String>, ignore_missing: bool) -> Node {
+        fold_include(self, ws, includes, ignore_missing)
+    }
+
+    /// The `{% import "macros.html" as macros %}`
+    fn fold_import_macro(&mut self, ws: WS, path: String, name: String) -> Node {
+        fold_import_macro(self, ws, path, name)
+    }
+
+    /// The `{% set val = something %}` tag
+    fn fold_set(&mut self, ws: WS, set: Set) -> Node {
+        fold_set(self, ws, set)
+    }
+
+    /// The text between `{% raw %}` and `{% endraw %}`
+    fn fold_raw(&mut self, left_ws: WS, content: String, right_ws: WS) -> Node {
+        fold_raw(self, left_ws, content, right_ws)
+    }
+
+    /// A filter section node `{{ filter name(param="value") }} content {{ endfilter }}`
+    fn fold_filter_section(
+        &mut self,
+        left_ws: WS,
+        filter_section: FilterSection,
+        right_ws: WS,
+    ) -> Node {
+        fold_filter_section(self, left_ws, filter_section, right_ws)
+    }
+
+    /// A `{% block name %}...{% endblock %}`
+    fn fold_block(&mut self, left_ws: WS, block: Block, right_ws: WS) -> Node {
+        fold_block(self, left_ws, block, right_ws)
+    }
+
+    /// A `{% for i in items %}...{% endfor %}`
+    fn fold_forloop(&mut self, left_ws: WS, forloop: Forloop, right_ws: WS) -> Node {
+        fold_forloop(self, left_ws, forloop, right_ws)
+    }
+
+    fn fold_forloop_expr(&mut self, expr: Expr) -> Expr {
+        fold_forloop_expr(self, expr)
+    }
+
+    /// A if/elif/else block, WS for the if/elif/else is directly in the struct
+    fn fold_if(&mut self, if_: If, ws: WS) -> Node {
+        fold_if(self, if_, ws)
+    }
+
+    fn fold_if_expr(&mut self, expr: Expr) -> Expr {
+        fold_if_expr(self, expr)
+    }
+
+    fn fold_else_if_expr(&mut self, expr: Expr) -> Expr {
+        fold_else_if_expr(self, expr)
+    }
+
+    /// The `{% break %}` tag
+    fn fold_break(&mut self, ws: WS) -> Node {
+        fold_break(self, ws)
+    }
+
+    /// The `{% continue %}` tag
+    fn fold_continue(&mut self, ws: WS) -> Node {
+        fold_continue(self, ws)
+    }
+
+    /// The `{# #} `comment tag and its content
+    fn fold_comment(&mut self, ws: WS, content: String) -> Node {
+        fold_comment(self, ws, content)
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        fold_expr(self, expr)
+    }
+
+    fn fold_expr_val(&mut self, expr_val: ExprVal) -> ExprVal {
+        fold_expr_val(self, expr_val)
+    }
+
+    fn fold_string_expr_val(&mut self, s: String) -> ExprVal {
+        fold_string_expr_val(self, s)
+    }
+
+    fn fold_int_expr_val(&mut self, i: i64) -> ExprVal {
+        fold_int_expr_val(self, i)
+    }
+
+    fn fold_float_expr_val(&mut self, f: f64) -> ExprVal {
+        fold_float_expr_val(self, f)
+    }
+
+    fn fold_bool_expr_val(&mut self, b: bool) -> ExprVal {
+        fold_bool_expr_val(self, b)
+    }
+
+    fn fold_ident_expr_val(&mut self, ident: String) -> ExprVal {
+        fold_ident_expr_val(self, ident)
+    }
+
+    fn fold_math_expr_val(&mut self, math_expr: MathExpr) -> ExprVal {
+        fold_math_expr_val(self, math_expr)
+    }
+
+    /// The left hand side of a math expression, `a` in `a + b`
+    fn fold_math_lhs_expr(&mut self, expr: Expr) -> Expr {
+        fold_math_lhs_expr(self, expr)
+    }
+
+    /// The right hand side of a math expression, `b` in `a + b`
+    fn fold_math_rhs_expr(&mut self, expr: Expr) -> Expr {
+        fold_math_rhs_expr(self, expr)
+    }
+
+    fn fold_logic_expr_val(&mut self, logic_expr: LogicExpr) -> ExprVal {
+        fold_logic_expr_val(self, logic_expr)
+    }
+
+    /// The left hand side of a logic expression, `a` in `a and b`
+    fn fold_logic_lhs_expr(&mut self, expr: Expr) -> Expr {
+        fold_logic_lhs_expr(self, expr)
+    }
+
+    /// The right hand side of a logic expression, `b` in `a and b`
+    fn fold_logic_rhs_expr(&mut self, expr: Expr) -> Expr {
+        fold_logic_rhs_expr(self, expr)
+    }
+
+    fn fold_test_expr_val(&mut self, test: Test) -> ExprVal {
+        fold_test_expr_val(self, test)
+    }
+
+    /// The tested variable, `a` in `a is defined`
+    fn fold_test_ident(&mut self, ident: String) -> String {
+        fold_test_ident(self, ident)
+    }
+
+    fn fold_array_expr_val(&mut self, array: Vec<Expr>) -> ExprVal {
+        fold_array_expr_val(self, array)
+    }
+
+    fn fold_string_concat_expr_val(&mut self, string_concat: StringConcat) -> ExprVal {
+        fold_string_concat_expr_val(self, string_concat)
+    }
+
+    /// Any of the values concatenated with `~`
+    fn fold_string_concat_value(&mut self, expr_val: ExprVal) -> ExprVal {
+        fold_string_concat_value(self, expr_val)
+    }
+
+    fn fold_macro_call(&mut self, macro_call: MacroCall) -> MacroCall {
+        fold_macro_call(self, macro_call)
+    }
+
+    fn fold_function_call(&mut self, function_call: FunctionCall) -> FunctionCall {
+        fold_function_call(self, function_call)
+    }
+
+    fn fold_in_expr_val(&mut self, in_: In) -> ExprVal {
+        fold_in_expr_val(self, in_)
+    }
+
+    /// The needle, `a` in `a in b`
+    fn fold_in_lhs_expr(&mut self, expr: Expr) -> Expr {
+        fold_in_lhs_expr(self, expr)
+    }
+
+    /// The haystack, `b` in `a in b`
+    fn fold_in_rhs_expr(&mut self, expr: Expr) -> Expr {
+        fold_in_rhs_expr(self, expr)
+    }
+}
+
+// Whole AST
+pub fn fold_ast<F: Fold + ?Sized>(folder: &mut F, ast: Ast) -> Ast {
+    let mut folded_ast = Vec::with_capacity(ast.len());
+
+    for node in ast {
+        folded_ast.extend(folder.fold_node(node));
+    }
+
+    folded_ast
+}
+
+/// Any node, returning several nodes (or none) allows for wrapping (or stripping) nodes
+pub fn fold_node<F: Fold + ?Sized>(folder: &mut F, node: Node) -> Ast {
+    let node = match node {
+        Node::Super => folder.fold_super(),
+        Node::Text(text) => folder.fold_text(text),
+        Node::VariableBlock(ws, expr) => folder.fold_variable_block(ws, expr),
+        Node::MacroDefinition(left_ws, macro_definition, right_ws) => {
+            folder.fold_macro_definition(left_ws, macro_definition, right_ws)
+        }
+        Node::Extends(ws, content) => folder.fold_extends(ws, content),
+        Node::Include(ws, includes, ignore_missing) => {
+            folder.fold_include(ws, includes, ignore_missing)
+        }
+        Node::ImportMacro(ws, path, name) => folder.fold_import_macro(ws, path, name),
+        Node::Set(ws, set) => folder.fold_set(ws, set),
+        Node::Raw(left_ws, content, right_ws) => folder.fold_raw(left_ws, content, right_ws),
+        Node::FilterSection(left_ws, filter_section, right_ws) => {
+            folder.fold_filter_section(left_ws, filter_section, right_ws)
+        }
+        Node::Block(left_ws, block, right_ws) => folder.fold_block(left_ws, block, right_ws),
+        Node::Forloop(left_ws, forloop, right_ws) => {
+            folder.fold_forloop(left_ws, forloop, right_ws)
+        }
+        Node::If(if_, ws) => folder.fold_if(if_, ws),
+        Node::Break(ws) => folder.fold_break(ws),
+        Node::Continue(ws) => folder.fold_continue(ws),
+        Node::Comment(ws, content) => folder.fold_comment(ws, content),
+    };
+
+    vec![node]
+}
+
+/// A call to `{{ super() }}` in a block
+pub fn fold_super<F: Fold + ?Sized>(_folder: &mut F) -> Node {
+    Node::Super
+}
+
+/// Some actual text
+pub fn fold_text<F: Fold + ?Sized>(_folder: &mut F, text: String) -> Node {
+    Node::Text(text)
+}
+
+/// A `{{ }}` block
+pub fn fold_variable_block<F: Fold + ?Sized>(folder: &mut F, ws: WS, expr: Expr) -> Node {
+    Node::VariableBlock(ws, folder.fold_expr(expr))
+}
+
+/// A `{% macro hello() %}...{% endmacro %}`
+pub fn fold_macro_definition<F: Fold + ?Sized>(
+    folder: &mut F,
+    left_ws: WS,
+    macro_definition: MacroDefinition,
+    right_ws: WS,
+) -> Node {
+    let MacroDefinition { name, args, body } = macro_definition;
+
+    enter_ancestor(folder, || Ancestor::MacroDefinition { name: name.clone() });
+
+    let body = folder.fold_ast(body);
+
+    let args = args
+        .into_iter()
+        .map(|(name, expr)| (name, expr.map(|expr| folder.fold_expr(expr))))
+        .collect();
+
+    exit_ancestor(folder);
+
+    Node::MacroDefinition(left_ws, MacroDefinition { name, args, body }, right_ws)
+}
+
+/// The `{% extends "blabla.html" %}` node, contains the template name
+pub fn fold_extends<F: Fold + ?Sized>(_folder: &mut F, ws: WS, content: String) -> Node {
+    Node::Extends(ws, content)
+}
+
+/// The `{% include "blabla.html" %}` node, contains the template name
+pub fn fold_include<F: Fold + ?Sized>(
+    _folder: &mut F,
+    ws: WS,
+    includes: Vec<String>,
+    ignore_missing: bool,
+) -> Node {
+    Node::Include(ws, includes, ignore_missing)
+}
+
+/// The `{% import "macros.html" as macros %}`
+pub fn fold_import_macro<F: Fold + ?Sized>(
+    _folder: &mut F,
+    ws: WS,
+    path: String,
+    name: String,
+) -> Node {
+    Node::ImportMacro(ws, path, name)
+}
+
+/// The `{% set val = something %}` tag
+pub fn fold_set<F: Fold + ?Sized>(folder: &mut F, ws: WS, set: Set) -> Node {
+    let Set { key, value, global } = set;
+
+    Node::Set(
+        ws,
+        Set {
+            key,
+            value: folder.fold_expr(value),
+            global,
+        },
+    )
+}
+
+/// The text between `{% raw %}` and `{% endraw %}`
+pub fn fold_raw<F: Fold + ?Sized>(
+    _folder: &mut F,
+    left_ws: WS,
+    content: String,
+    right_ws: WS,
+) -> Node {
+    Node::Raw(left_ws, content, right_ws)
+}
+
+/// A filter section node `{{ filter name(param="value") }} content {{ endfilter }}`
+pub fn fold_filter_section<F: Fold + ?Sized>(
+    folder: &mut F,
+    left_ws: WS,
+    filter_section: FilterSection,
+    right_ws: WS,
+) -> Node {
+    let FilterSection { filter, body } = filter_section;
+
+    enter_ancestor(folder, || Ancestor::FilterSection {
+        name: filter.name.clone(),
+    });
+    let body = folder.fold_ast(body);
+    exit_ancestor(folder);
+
+    let filter = folder.fold_function_call(filter);
+
+    Node::FilterSection(left_ws, FilterSection { filter, body }, right_ws)
+}
+
+/// A `{% block name %}...{% endblock %}`
+pub fn fold_block<F: Fold + ?Sized>(
+    folder: &mut F,
+    left_ws: WS,
+    block: Block,
+    right_ws: WS,
+) -> Node {
+    let Block { name, body } = block;
+
+    enter_ancestor(folder, || Ancestor::Block { name: name.clone() });
+    let body = folder.fold_ast(body);
+    exit_ancestor(folder);
+
+    Node::Block(left_ws, Block { name, body }, right_ws)
+}
+
+/// A `{% for i in items %}...{% endfor %}`
+pub fn fold_forloop<F: Fold + ?Sized>(
+    folder: &mut F,
+    left_ws: WS,
+    forloop: Forloop,
+    right_ws: WS,
+) -> Node {
+    let Forloop {
+        key,
+        value,
+        container,
+        body,
+        empty_body,
+    } = forloop;
+
+    let container = folder.fold_forloop_expr(container);
+
+    enter_ancestor(folder, || Ancestor::Forloop {
+        key: key.clone(),
+        value: value.clone(),
+    });
+    let body = folder.fold_ast(body);
+    exit_ancestor(folder);
+
+    let empty_body = empty_body.map(|empty_body| {
+        enter_ancestor(folder, || Ancestor::ForloopEmpty);
+        let empty_body = folder.fold_ast(empty_body);
+        exit_ancestor(folder);
+
+        empty_body
+    });
+
+    Node::Forloop(
+        left_ws,
+        Forloop {
+            key,
+            value,
+            container,
+            body,
+            empty_body,
+        },
+        right_ws,
+    )
+}
+
+pub fn fold_forloop_expr<F: Fold + ?Sized>(folder: &mut F, expr: Expr) -> Expr {
+    folder.fold_expr(expr)
+}
+
+/// A if/elif/else block, WS for the if/elif/else is directly in the struct
+pub fn fold_if<F: Fold + ?Sized>(folder: &mut F, if_: If, ws: WS) -> Node {
+    let If {
+        conditions,
+        otherwise,
+    } = if_;
+
+    let conditions = conditions
+        .into_iter()
+        .enumerate()
+        .map(|(index, (ws, expr, ast))| {
+            enter_ancestor(folder, || Ancestor::If { branch: index });
+
+            let expr = if index == 0 {
+                folder.fold_if_expr(expr)
+            } else {
+                folder.fold_else_if_expr(expr)
+            };
+            let ast = folder.fold_ast(ast);
+
+            exit_ancestor(folder);
+
+            (ws, expr, ast)
+        })
+        .collect();
+
+    let otherwise = otherwise.map(|(ws, ast)| {
+        enter_ancestor(folder, || Ancestor::IfOtherwise);
+        let ast = folder.fold_ast(ast);
+        exit_ancestor(folder);
+
+        (ws, ast)
+    });
+
+    Node::If(
+        If {
+            conditions,
+            otherwise,
+        },
+        ws,
+    )
+}
+
+pub fn fold_if_expr<F: Fold + ?Sized>(folder: &mut F, expr: Expr) -> Expr {
+    folder.fold_expr(expr)
+}
+
+pub fn fold_else_if_expr<F: Fold + ?Sized>(folder: &mut F, expr: Expr) -> Expr {
+    folder.fold_expr(expr)
+}
+
+/// The `{% break %}` tag
+pub fn fold_break<F: Fold + ?Sized>(_folder: &mut F, ws: WS) -> Node {
+    Node::Break(ws)
+}
+
+/// The `{% continue %}` tag
+pub fn fold_continue<F: Fold + ?Sized>(_folder: &mut F, ws: WS) -> Node {
+    Node::Continue(ws)
+}
+
+/// The `{# #} `comment tag and its content
+pub fn fold_comment<F: Fold + ?Sized>(_folder: &mut F, ws: WS, content: String) -> Node {
+    Node::Comment(ws, content)
+}
+
+pub fn fold_expr<F: Fold + ?Sized>(folder: &mut F, expr: Expr) -> Expr {
+    let Expr {
+        val,
+        negated,
+        filters,
+    } = expr;
+
+    let filters = filters
+        .into_iter()
+        .map(|function_call| folder.fold_function_call(function_call))
+        .collect();
+
+    Expr {
+        val: folder.fold_expr_val(val),
+        negated,
+        filters,
+    }
+}
+
+pub fn fold_expr_val<F: Fold + ?Sized>(folder: &mut F, expr_val: ExprVal) -> ExprVal {
+    match expr_val {
+        ExprVal::String(s) => folder.fold_string_expr_val(s),
+        ExprVal::Int(i) => folder.fold_int_expr_val(i),
+        ExprVal::Float(f) => folder.fold_float_expr_val(f),
+        ExprVal::Bool(b) => folder.fold_bool_expr_val(b),
+        ExprVal::Ident(ident) => folder.fold_ident_expr_val(ident),
+        ExprVal::Math(math_expr) => folder.fold_math_expr_val(math_expr),
+        ExprVal::Logic(logic_expr) => folder.fold_logic_expr_val(logic_expr),
+        ExprVal::Test(test) => folder.fold_test_expr_val(test),
+        ExprVal::MacroCall(macro_call) => ExprVal::MacroCall(folder.fold_macro_call(macro_call)),
+        ExprVal::FunctionCall(function_call) => {
+            ExprVal::FunctionCall(folder.fold_function_call(function_call))
+        }
+        ExprVal::Array(array) => folder.fold_array_expr_val(array),
+        ExprVal::StringConcat(string_concat) => folder.fold_string_concat_expr_val(string_concat),
+        ExprVal::In(in_) => folder.fold_in_expr_val(in_),
+    }
+}
+
+pub fn fold_string_expr_val<F: Fold + ?Sized>(_folder: &mut F, s: String) -> ExprVal {
+    ExprVal::String(s)
+}
+
+pub fn fold_int_expr_val<F: Fold + ?Sized>(_folder: &mut F, i: i64) -> ExprVal {
+    ExprVal::Int(i)
+}
+
+pub fn fold_float_expr_val<F: Fold + ?Sized>(_folder: &mut F, f: f64) -> ExprVal {
+    ExprVal::Float(f)
+}
+
+pub fn fold_bool_expr_val<F: Fold + ?Sized>(_folder: &mut F, b: bool) -> ExprVal {
+    ExprVal::Bool(b)
+}
+
+pub fn fold_ident_expr_val<F: Fold + ?Sized>(_folder: &mut F, ident: String) -> ExprVal {
+    ExprVal::Ident(ident)
+}
+
+pub fn fold_math_expr_val<F: Fold + ?Sized>(folder: &mut F, math_expr: MathExpr) -> ExprVal {
+    let MathExpr { lhs, rhs, operator } = math_expr;
+
+    ExprVal::Math(MathExpr {
+        lhs: Box::new(folder.fold_math_lhs_expr(*lhs)),
+        rhs: Box::new(folder.fold_math_rhs_expr(*rhs)),
+        operator,
+    })
+}
+
+/// The left hand side of a math expression, `a` in `a + b`
+pub fn fold_math_lhs_expr<F: Fold + ?Sized>(folder: &mut F, expr: Expr) -> Expr {
+    folder.fold_expr(expr)
+}
+
+/// The right hand side of a math expression, `b` in `a + b`
+pub fn fold_math_rhs_expr<F: Fold + ?Sized>(folder: &mut F, expr: Expr) -> Expr {
+    folder.fold_expr(expr)
+}
+
+pub fn fold_logic_expr_val<F: Fold + ?Sized>(folder: &mut F, logic_expr: LogicExpr) -> ExprVal {
+    let LogicExpr { lhs, rhs, operator } = logic_expr;
+
+    ExprVal::Logic(LogicExpr {
+        lhs: Box::new(folder.fold_logic_lhs_expr(*lhs)),
+        rhs: Box::new(folder.fold_logic_rhs_expr(*rhs)),
+        operator,
+    })
+}
+
+/// The left hand side of a logic expression, `a` in `a and b`
+pub fn fold_logic_lhs_expr<F: Fold + ?Sized>(folder: &mut F, expr: Expr) -> Expr {
+    folder.fold_expr(expr)
+}
+
+/// The right hand side of a logic expression, `b` in `a and b`
+pub fn fold_logic_rhs_expr<F: Fold + ?Sized>(folder: &mut F, expr: Expr) -> Expr {
+    folder.fold_expr(expr)
+}
+
+pub fn fold_test_expr_val<F: Fold + ?Sized>(folder: &mut F, test: Test) -> ExprVal {
+    let Test {
+        ident,
+        negated,
+        name,
+        args,
+    } = test;
+
+    let ident = folder.fold_test_ident(ident);
+
+    let args = args
+        .into_iter()
+        .map(|expr| folder.fold_expr(expr))
+        .collect();
+
+    ExprVal::Test(Test {
+        ident,
+        negated,
+        name,
+        args,
+    })
+}
+
+/// The tested variable, `a` in `a is defined`
+pub fn fold_test_ident<F: Fold + ?Sized>(_folder: &mut F, ident: String) -> String {
+    ident
+}
+
+pub fn fold_array_expr_val<F: Fold + ?Sized>(folder: &mut F, array: Vec<Expr>) -> ExprVal {
+    ExprVal::Array(
+        array
+            .into_iter()
+            .map(|expr| folder.fold_expr(expr))
+            .collect(),
+    )
+}
+
+pub fn fold_string_concat_expr_val<F: Fold + ?Sized>(
+    folder: &mut F,
+    string_concat: StringConcat,
+) -> ExprVal {
+    let values = string_concat
+        .values
+        .into_iter()
+        .map(|expr_val| folder.fold_string_concat_value(expr_val))
+        .collect();
+
+    ExprVal::StringConcat(StringConcat { values })
+}
+
+/// Any of the values concatenated with `~`
+pub fn fold_string_concat_value<F: Fold + ?Sized>(folder: &mut F, expr_val: ExprVal) -> ExprVal {
+    folder.fold_expr_val(expr_val)
+}
+
+pub fn fold_macro_call<F: Fold + ?Sized>(folder: &mut F, macro_call: MacroCall) -> MacroCall {
+    let MacroCall {
+        namespace,
+        name,
+        args,
+    } = macro_call;
+
+    let args = args
+        .into_iter()
+        .map(|(name, expr)| (name, folder.fold_expr(expr)))
+        .collect();
+
+    MacroCall {
+        namespace,
+        name,
+        args,
+    }
+}
+
+pub fn fold_function_call<F: Fold + ?Sized>(
+    folder: &mut F,
+    function_call: FunctionCall,
+) -> FunctionCall {
+    let FunctionCall { name, args } = function_call;
+
+    let args = args
+        .into_iter()
+        .map(|(name, expr)| (name, folder.fold_expr(expr)))
+        .collect();
+
+    FunctionCall { name, args }
+}
+
+pub fn fold_in_expr_val<F: Fold + ?Sized>(folder: &mut F, in_: In) -> ExprVal {
+    let In { lhs, rhs, negated } = in_;
+
+    ExprVal::In(In {
+        lhs: Box::new(folder.fold_in_lhs_expr(*lhs)),
+        rhs: Box::new(folder.fold_in_rhs_expr(*rhs)),
+        negated,
+    })
+}
+
+/// The needle, `a` in `a in b`
+pub fn fold_in_lhs_expr<F: Fold + ?Sized>(folder: &mut F, expr: Expr) -> Expr {
+    folder.fold_expr(expr)
+}
+
+/// The haystack, `b` in `a in b`
+pub fn fold_in_rhs_expr<F: Fold + ?Sized>(folder: &mut F, expr: Expr) -> Expr {
+    folder.fold_expr(expr)
+}
+
+fn enter_ancestor<F: Fold + ?Sized>(folder: &mut F, ancestor: impl FnOnce() -> Ancestor) {
+    if let Some(ancestry) = folder.ancestry_mut() {
+        ancestry.push(ancestor());
+    }
+}
+
+fn exit_ancestor<F: Fold + ?Sized>(folder: &mut F) {
+    if let Some(ancestry) = folder.ancestry_mut() {
+        ancestry.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tera::Template;
+
+    use super::*;
+
+    fn parse(source: &str) -> Ast {
+        Template::new("", None, source).unwrap().ast
+    }
+
+    /// Reads the data from a `cv` object, and removes the comments
+    struct Namespace;
+
+    impl Fold for Namespace {
+        fn fold_node(&mut self, node: Node) -> Ast {
+            match node {
+                Node::Comment(..) => Vec::new(),
+                node => fold_node(self, node),
+            }
+        }
+
+        fn fold_ident_expr_val(&mut self, ident: String) -> ExprVal {
+            ExprVal::Ident(format!("cv.{ident}"))
+        }
+
+        fn fold_test_ident(&mut self, ident: String) -> String {
+            format!("cv.{ident}")
+        }
+    }
+
+    #[test]
+    fn rewrites_nested_expressions() {
+        let ast = parse(
+            "{{ a + b * c }}{% if d and not e or f in g %}{{ h ~ 'x' }}{% endif %}\
+             {{ i | default(value=j) }}{{ m::n(x=k) }}{% if l is defined and o is divisibleby(p) %}{% endif %}",
+        );
+
+        assert_eq!(
+            Namespace.fold_ast(ast),
+            parse(
+                "{{ cv.a + cv.b * cv.c }}{% if cv.d and not cv.e or cv.f in cv.g %}{{ cv.h ~ 'x' }}{% endif %}\
+                 {{ cv.i | default(value=cv.j) }}{{ m::n(x=cv.k) }}\
+                 {% if cv.l is defined and cv.o is divisibleby(cv.p) %}{% endif %}"
+            )
+        );
+    }
+
+    #[test]
+    fn rewrites_bodies() {
+        let ast = parse(
+            "{% for x in a %}{{ b }}{% else %}{{ c }}{% endfor %}{% set y = d %}\
+             {% block z %}{% filter upper %}{{ e }}{% endfilter %}{% endblock %}",
+        );
+
+        assert_eq!(
+            Namespace.fold_ast(ast),
+            parse(
+                "{% for x in cv.a %}{{ cv.b }}{% else %}{{ cv.c }}{% endfor %}{% set y = cv.d %}\
+                 {% block z %}{% filter upper %}{{ cv.e }}{% endfilter %}{% endblock %}"
+            )
+        );
+    }
+
+    #[test]
+    fn removes_nodes() {
+        // Tera drops a comment at the start of the template, but keeps the others
+        let ast = parse("{# header #}a{# comment #}b");
+
+        assert!(matches!(ast[..], [_, Node::Comment(..), _]));
+        assert_eq!(
+            Namespace.fold_ast(ast),
+            [Node::Text("a".to_string()), Node::Text("b".to_string())]
+        );
+    }
+
+    #[test]
+    fn keeps_the_ast_by_default() {
+        struct Identity;
+
+        impl Fold for Identity {}
+
+        let ast = parse(
+            "{% macro m(n, d=1) %}{{ n }}{% endmacro %}{% raw %}{{ x }}{% endraw %}\
+             {%- if a -%}{{ b | round }}{% elif c %}{% for x in d %}{% break %}{% endfor %}{% endif %}",
+        );
+
+        assert_eq!(Identity.fold_ast(ast.clone()), ast);
+    }
+
+    #[test]
+    fn tracks_the_ancestry() {
+        /// Reads the data from a `cv` object, except inside the loops
+        #[derive(Default)]
+        struct NamespaceOutsideLoops(Ancestry);
+
+        impl Fold for NamespaceOutsideLoops {
+            fn ancestry_mut(&mut self) -> Option<&mut Ancestry> {
+                Some(&mut self.0)
+            }
+
+            fn fold_ident_expr_val(&mut self, ident: String) -> ExprVal {
+                if self.0.is_in_forloop() {
+                    ExprVal::Ident(ident)
+                } else {
+                    ExprVal::Ident(format!("cv.{ident}"))
+                }
+            }
+        }
+
+        let ast = parse(
+            "{% block z %}{% for x in a %}{% if b %}{{ x }}{% endif %}{% else %}{{ c }}{% endfor %}{% endblock %}",
+        );
+        let mut folder = NamespaceOutsideLoops::default();
+
+        assert_eq!(
+            folder.fold_ast(ast),
+            parse(
+                "{% block z %}{% for x in cv.a %}{% if b %}{{ x }}{% endif %}{% else %}{{ cv.c }}{% endfor %}{% endblock %}"
+            )
+        );
+        assert!(folder.0.is_empty());
+    }
+}
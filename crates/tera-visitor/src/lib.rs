@@ -1,14 +1,33 @@
 #![deny(clippy::all)]
 #![deny(clippy::pedantic)]
 
+use std::cell::RefCell;
+
 use tera::ast::{
     Block, Expr, ExprVal, FilterSection, Forloop, FunctionCall, If, In, LogicExpr, MacroCall,
     MacroDefinition, MathExpr, Node, Set, StringConcat, Test, WS,
 };
 
+pub use crate::{
+    ancestry::{Ancestor, Ancestry},
+    fold::Fold,
+    try_visitor::{TryVisitor, TryVisitorMut},
+};
+
+pub mod ancestry;
+pub mod fold;
+pub mod printer;
+pub mod source;
+pub mod try_visitor;
+
 pub type Ast = Vec<Node>;
 
 pub trait Visitor {
+    /// The ancestors of the visited node, kept up to date by the walkers if some storage is returned
+    fn ancestry(&self) -> Option<&RefCell<Ancestry>> {
+        None
+    }
+
     // Whole AST
     fn visit_ast(&self, ast: &Ast) {
         visit_ast(self, ast);
@@ -143,10 +162,30 @@ pub trait Visitor {
         visit_math_expr_val(self, math_expr);
     }
 
+    /// The left hand side of a math expression, `a` in `a + b`
+    fn visit_math_lhs_expr(&self, expr: &Expr) {
+        visit_math_lhs_expr(self, expr);
+    }
+
+    /// The right hand side of a math expression, `b` in `a + b`
+    fn visit_math_rhs_expr(&self, expr: &Expr) {
+        visit_math_rhs_expr(self, expr);
+    }
+
     fn visit_logic_expr_val(&self, logic_expr: &LogicExpr) {
         visit_logic_expr_val(self, logic_expr);
     }
 
+    /// The left hand side of a logic expression, `a` in `a and b`
+    fn visit_logic_lhs_expr(&self, expr: &Expr) {
+        visit_logic_lhs_expr(self, expr);
+    }
+
+    /// The right hand side of a logic expression, `b` in `a and b`
+    fn visit_logic_rhs_expr(&self, expr: &Expr) {
+        visit_logic_rhs_expr(self, expr);
+    }
+
     fn visit_test_expr_val(&self, test: &Test) {
         visit_test_expr_val(self, test);
     }
@@ -159,6 +198,11 @@ pub trait Visitor {
         visit_string_concat_expr_val(self, string_concat);
     }
 
+    /// Any of the values concatenated with `~`
+    fn visit_string_concat_value(&self, expr_val: &ExprVal) {
+        visit_string_concat_value(self, expr_val);
+    }
+
     fn visit_macro_call(&self, macro_call: &MacroCall) {
         visit_macro_call(self, macro_call);
     }
@@ -170,6 +214,16 @@ pub trait Visitor {
     fn visit_in_expr_val(&self, in_: &In) {
         visit_in_expr_val(self, in_);
     }
+
+    /// The needle, `a` in `a in b`
+    fn visit_in_lhs_expr(&self, expr: &Expr) {
+        visit_in_lhs_expr(self, expr);
+    }
+
+    /// The haystack, `b` in `a in b`
+    fn visit_in_rhs_expr(&self, expr: &Expr) {
+        visit_in_rhs_expr(self, expr);
+    }
 }
 
 // Whole AST
@@ -222,11 +276,17 @@ pub fn visit_macro_definition<V: Visitor + ?Sized>(
     macro_definition: &MacroDefinition,
     _right_ws: &WS,
 ) {
+    enter_ancestor(visitor, || Ancestor::MacroDefinition {
+        name: macro_definition.name.clone(),
+    });
+
     visitor.visit_ast(&macro_definition.body);
 
     for expr in macro_definition.args.values().flatten() {
         visitor.visit_expr(expr);
     }
+
+    exit_ancestor(visitor);
 }
 
 /// The `{% extends "blabla.html" %}` node, contains the template name
@@ -260,13 +320,22 @@ pub fn visit_filter_section<V: Visitor + ?Sized>(
     filter_section: &FilterSection,
     _right_ws: &WS,
 ) {
+    enter_ancestor(visitor, || Ancestor::FilterSection {
+        name: filter_section.filter.name.clone(),
+    });
     visitor.visit_ast(&filter_section.body);
+    exit_ancestor(visitor);
+
     visitor.visit_function_call(&filter_section.filter);
 }
 
 /// A `{% block name %}...{% endblock %}`
 pub fn visit_block<V: Visitor + ?Sized>(visitor: &V, _left_ws: &WS, block: &Block, _right_ws: &WS) {
+    enter_ancestor(visitor, || Ancestor::Block {
+        name: block.name.clone(),
+    });
     visitor.visit_ast(&block.body);
+    exit_ancestor(visitor);
 }
 
 /// A `{% for i in items %}...{% endfor %}`
@@ -277,10 +346,18 @@ pub fn visit_forloop<V: Visitor + ?Sized>(
     _right_ws: &WS,
 ) {
     visitor.visit_forloop_expr(&forloop.container);
+
+    enter_ancestor(visitor, || Ancestor::Forloop {
+        key: forloop.key.clone(),
+        value: forloop.value.clone(),
+    });
     visitor.visit_ast(&forloop.body);
+    exit_ancestor(visitor);
 
     if let Some(empty_body) = &forloop.empty_body {
+        enter_ancestor(visitor, || Ancestor::ForloopEmpty);
         visitor.visit_ast(empty_body);
+        exit_ancestor(visitor);
     }
 }
 
@@ -291,6 +368,8 @@ pub fn visit_forloop_expr<V: Visitor + ?Sized>(visitor: &V, expr: &Expr) {
 /// A if/elif/else block, WS for the if/elif/else is directly in the struct
 pub fn visit_if<V: Visitor + ?Sized>(visitor: &V, if_: &If, _ws: &WS) {
     for (index, (_, expr, ast)) in if_.conditions.iter().enumerate() {
+        enter_ancestor(visitor, || Ancestor::If { branch: index });
+
         if index == 0 {
             visitor.visit_if_expr(expr);
         } else {
@@ -298,10 +377,14 @@ pub fn visit_if<V: Visitor + ?Sized>(visitor: &V, if_: &If, _ws: &WS) {
         }
 
         visitor.visit_ast(ast);
+
+        exit_ancestor(visitor);
     }
 
     if let Some((_, ast)) = &if_.otherwise {
+        enter_ancestor(visitor, || Ancestor::IfOtherwise);
         visitor.visit_ast(ast);
+        exit_ancestor(visitor);
     }
 }
 
@@ -358,12 +441,34 @@ pub fn visit_bool_expr_val<V: Visitor + ?Sized>(_visitor: &V, _b: bool) {}
 
 pub fn visit_ident_expr_val<V: Visitor + ?Sized>(_visitor: &V, _ident: &str) {}
 
-pub fn visit_math_expr_val<V: Visitor + ?Sized>(_visitor: &V, _math_expr: &MathExpr) {
-    // TODO: Visit lhs/rhs
+pub fn visit_math_expr_val<V: Visitor + ?Sized>(visitor: &V, math_expr: &MathExpr) {
+    visitor.visit_math_lhs_expr(&math_expr.lhs);
+    visitor.visit_math_rhs_expr(&math_expr.rhs);
+}
+
+/// The left hand side of a math expression, `a` in `a + b`
+pub fn visit_math_lhs_expr<V: Visitor + ?Sized>(visitor: &V, expr: &Expr) {
+    visitor.visit_expr(expr);
 }
 
-pub fn visit_logic_expr_val<V: Visitor + ?Sized>(_visitor: &V, _logic_expr: &LogicExpr) {
-    // TODO: Visit lhs/rhs
+/// The right hand side of a math expression, `b` in `a + b`
+pub fn visit_math_rhs_expr<V: Visitor + ?Sized>(visitor: &V, expr: &Expr) {
+    visitor.visit_expr(expr);
+}
+
+pub fn visit_logic_expr_val<V: Visitor + ?Sized>(visitor: &V, logic_expr: &LogicExpr) {
+    visitor.visit_logic_lhs_expr(&logic_expr.lhs);
+    visitor.visit_logic_rhs_expr(&logic_expr.rhs);
+}
+
+/// The left hand side of a logic expression, `a` in `a and b`
+pub fn visit_logic_lhs_expr<V: Visitor + ?Sized>(visitor: &V, expr: &Expr) {
+    visitor.visit_expr(expr);
+}
+
+/// The right hand side of a logic expression, `b` in `a and b`
+pub fn visit_logic_rhs_expr<V: Visitor + ?Sized>(visitor: &V, expr: &Expr) {
+    visitor.visit_expr(expr);
 }
 
 pub fn visit_test_expr_val<V: Visitor + ?Sized>(visitor: &V, test: &Test) {
@@ -379,9 +484,17 @@ pub fn visit_array_expr_val<V: Visitor + ?Sized>(visitor: &V, array: &Vec<Expr>)
 }
 
 pub fn visit_string_concat_expr_val<V: Visitor + ?Sized>(
-    _visitor: &V,
-    _string_concat: &StringConcat,
+    visitor: &V,
+    string_concat: &StringConcat,
 ) {
+    for expr_val in &string_concat.values {
+        visitor.visit_string_concat_value(expr_val);
+    }
+}
+
+/// Any of the values concatenated with `~`
+pub fn visit_string_concat_value<V: Visitor + ?Sized>(visitor: &V, expr_val: &ExprVal) {
+    visitor.visit_expr_val(expr_val);
 }
 
 pub fn visit_macro_call<V: Visitor + ?Sized>(visitor: &V, macro_call: &MacroCall) {
@@ -396,11 +509,27 @@ pub fn visit_function_call<V: Visitor + ?Sized>(visitor: &V, function_call: &Fun
     }
 }
 
-pub fn visit_in_expr_val<V: Visitor + ?Sized>(_visitor: &V, _in_: &In) {
-    // TODO: Visit lhs/rhs
+pub fn visit_in_expr_val<V: Visitor + ?Sized>(visitor: &V, in_: &In) {
+    visitor.visit_in_lhs_expr(&in_.lhs);
+    visitor.visit_in_rhs_expr(&in_.rhs);
+}
+
+/// The needle, `a` in `a in b`
+pub fn visit_in_lhs_expr<V: Visitor + ?Sized>(visitor: &V, expr: &Expr) {
+    visitor.visit_expr(expr);
+}
+
+/// The haystack, `b` in `a in b`
+pub fn visit_in_rhs_expr<V: Visitor + ?Sized>(visitor: &V, expr: &Expr) {
+    visitor.visit_expr(expr);
 }
 
 pub trait VisitorMut {
+    /// The ancestors of the visited node, kept up to date by the walkers if some storage is returned
+    fn ancestry_mut(&mut self) -> Option<&mut Ancestry> {
+        None
+    }
+
     // Whole AST
     fn visit_ast_mut(&mut self, ast: &Ast) {
         visit_ast_mut(self, ast);
@@ -540,10 +669,30 @@ pub trait VisitorMut {
         visit_math_expr_val_mut(self, math_expr);
     }
 
+    /// The left hand side of a math expression, `a` in `a + b`
+    fn visit_math_lhs_expr_mut(&mut self, expr: &Expr) {
+        visit_math_lhs_expr_mut(self, expr);
+    }
+
+    /// The right hand side of a math expression, `b` in `a + b`
+    fn visit_math_rhs_expr_mut(&mut self, expr: &Expr) {
+        visit_math_rhs_expr_mut(self, expr);
+    }
+
     fn visit_logic_expr_val_mut(&mut self, logic_expr: &LogicExpr) {
         visit_logic_expr_val_mut(self, logic_expr);
     }
 
+    /// The left hand side of a logic expression, `a` in `a and b`
+    fn visit_logic_lhs_expr_mut(&mut self, expr: &Expr) {
+        visit_logic_lhs_expr_mut(self, expr);
+    }
+
+    /// The right hand side of a logic expression, `b` in `a and b`
+    fn visit_logic_rhs_expr_mut(&mut self, expr: &Expr) {
+        visit_logic_rhs_expr_mut(self, expr);
+    }
+
     fn visit_test_expr_val_mut(&mut self, test: &Test) {
         visit_test_expr_val_mut(self, test);
     }
@@ -556,6 +705,11 @@ pub trait VisitorMut {
         visit_string_concat_expr_val_mut(self, string_concat);
     }
 
+    /// Any of the values concatenated with `~`
+    fn visit_string_concat_value_mut(&mut self, expr_val: &ExprVal) {
+        visit_string_concat_value_mut(self, expr_val);
+    }
+
     fn visit_macro_call_mut(&mut self, macro_call: &MacroCall) {
         visit_macro_call_mut(self, macro_call);
     }
@@ -567,6 +721,16 @@ pub trait VisitorMut {
     fn visit_in_expr_val_mut(&mut self, in_: &In) {
         visit_in_expr_val_mut(self, in_);
     }
+
+    /// The needle, `a` in `a in b`
+    fn visit_in_lhs_expr_mut(&mut self, expr: &Expr) {
+        visit_in_lhs_expr_mut(self, expr);
+    }
+
+    /// The haystack, `b` in `a in b`
+    fn visit_in_rhs_expr_mut(&mut self, expr: &Expr) {
+        visit_in_rhs_expr_mut(self, expr);
+    }
 }
 
 // Whole AST
@@ -623,11 +787,17 @@ pub fn visit_macro_definition_mut<V: VisitorMut + ?Sized>(
     macro_definition: &MacroDefinition,
     _right_ws: &WS,
 ) {
+    enter_ancestor_mut(visitor, || Ancestor::MacroDefinition {
+        name: macro_definition.name.clone(),
+    });
+
     visitor.visit_ast_mut(&macro_definition.body);
 
     for expr in macro_definition.args.values().flatten() {
         visitor.visit_expr_mut(expr);
     }
+
+    exit_ancestor_mut(visitor);
 }
 
 /// The `{% extends "blabla.html" %}` node, contains the template name
@@ -672,7 +842,12 @@ pub fn visit_filter_section_mut<V: VisitorMut + ?Sized>(
     filter_section: &FilterSection,
     _right_ws: &WS,
 ) {
+    enter_ancestor_mut(visitor, || Ancestor::FilterSection {
+        name: filter_section.filter.name.clone(),
+    });
     visitor.visit_ast_mut(&filter_section.body);
+    exit_ancestor_mut(visitor);
+
     visitor.visit_function_call_mut(&filter_section.filter);
 }
 
@@ -683,7 +858,11 @@ pub fn visit_block_mut<V: VisitorMut + ?Sized>(
     block: &Block,
     _right_ws: &WS,
 ) {
+    enter_ancestor_mut(visitor, || Ancestor::Block {
+        name: block.name.clone(),
+    });
     visitor.visit_ast_mut(&block.body);
+    exit_ancestor_mut(visitor);
 }
 
 /// A `{% for i in items %}...{% endfor %}`
@@ -694,10 +873,18 @@ pub fn visit_forloop_mut<V: VisitorMut + ?Sized>(
     _right_ws: &WS,
 ) {
     visitor.visit_forloop_expr_mut(&forloop.container);
+
+    enter_ancestor_mut(visitor, || Ancestor::Forloop {
+        key: forloop.key.clone(),
+        value: forloop.value.clone(),
+    });
     visitor.visit_ast_mut(&forloop.body);
+    exit_ancestor_mut(visitor);
 
     if let Some(empty_body) = &forloop.empty_body {
+        enter_ancestor_mut(visitor, || Ancestor::ForloopEmpty);
         visitor.visit_ast_mut(empty_body);
+        exit_ancestor_mut(visitor);
     }
 }
 
@@ -708,6 +895,8 @@ pub fn visit_forloop_expr_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &Ex
 /// A if/elif/else block, WS for the if/elif/else is directly in the struct
 pub fn visit_if_mut<V: VisitorMut + ?Sized>(visitor: &mut V, if_: &If, _ws: &WS) {
     for (index, (_, expr, ast)) in if_.conditions.iter().enumerate() {
+        enter_ancestor_mut(visitor, || Ancestor::If { branch: index });
+
         if index == 0 {
             visitor.visit_if_expr_mut(expr);
         } else {
@@ -715,10 +904,14 @@ pub fn visit_if_mut<V: VisitorMut + ?Sized>(visitor: &mut V, if_: &If, _ws: &WS)
         }
 
         visitor.visit_ast_mut(ast);
+
+        exit_ancestor_mut(visitor);
     }
 
     if let Some((_, ast)) = &if_.otherwise {
+        enter_ancestor_mut(visitor, || Ancestor::IfOtherwise);
         visitor.visit_ast_mut(ast);
+        exit_ancestor_mut(visitor);
     }
 }
 
@@ -777,12 +970,34 @@ pub fn visit_bool_expr_val_mut<V: VisitorMut + ?Sized>(_visitor: &mut V, _b: boo
 
 pub fn visit_ident_expr_val_mut<V: VisitorMut + ?Sized>(_visitor: &mut V, _ident: &str) {}
 
-pub fn visit_math_expr_val_mut<V: VisitorMut + ?Sized>(_visitor: &mut V, _math_expr: &MathExpr) {
-    // TODO: Visit lhs/rhs
+pub fn visit_math_expr_val_mut<V: VisitorMut + ?Sized>(visitor: &mut V, math_expr: &MathExpr) {
+    visitor.visit_math_lhs_expr_mut(&math_expr.lhs);
+    visitor.visit_math_rhs_expr_mut(&math_expr.rhs);
+}
+
+/// The left hand side of a math expression, `a` in `a + b`
+pub fn visit_math_lhs_expr_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &Expr) {
+    visitor.visit_expr_mut(expr);
+}
+
+/// The right hand side of a math expression, `b` in `a + b`
+pub fn visit_math_rhs_expr_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &Expr) {
+    visitor.visit_expr_mut(expr);
+}
+
+pub fn visit_logic_expr_val_mut<V: VisitorMut + ?Sized>(visitor: &mut V, logic_expr: &LogicExpr) {
+    visitor.visit_logic_lhs_expr_mut(&logic_expr.lhs);
+    visitor.visit_logic_rhs_expr_mut(&logic_expr.rhs);
+}
+
+/// The left hand side of a logic expression, `a` in `a and b`
+pub fn visit_logic_lhs_expr_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &Expr) {
+    visitor.visit_expr_mut(expr);
 }
 
-pub fn visit_logic_expr_val_mut<V: VisitorMut + ?Sized>(_visitor: &mut V, _logic_expr: &LogicExpr) {
-    // TODO: Visit lhs/rhs
+/// The right hand side of a logic expression, `b` in `a and b`
+pub fn visit_logic_rhs_expr_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &Expr) {
+    visitor.visit_expr_mut(expr);
 }
 
 pub fn visit_test_expr_val_mut<V: VisitorMut + ?Sized>(visitor: &mut V, test: &Test) {
@@ -798,9 +1013,17 @@ pub fn visit_array_expr_val_mut<V: VisitorMut + ?Sized>(visitor: &mut V, array:
 }
 
 pub fn visit_string_concat_expr_val_mut<V: VisitorMut + ?Sized>(
-    _visitor: &mut V,
-    _string_concat: &StringConcat,
+    visitor: &mut V,
+    string_concat: &StringConcat,
 ) {
+    for expr_val in &string_concat.values {
+        visitor.visit_string_concat_value_mut(expr_val);
+    }
+}
+
+/// Any of the values concatenated with `~`
+pub fn visit_string_concat_value_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr_val: &ExprVal) {
+    visitor.visit_expr_val_mut(expr_val);
 }
 
 pub fn visit_macro_call_mut<V: VisitorMut + ?Sized>(visitor: &mut V, macro_call: &MacroCall) {
@@ -818,6 +1041,207 @@ pub fn visit_function_call_mut<V: VisitorMut + ?Sized>(
     }
 }
 
-pub fn visit_in_expr_val_mut<V: VisitorMut + ?Sized>(_visitor: &mut V, _in_: &In) {
-    // TODO: Visit lhs/rhs
+pub fn visit_in_expr_val_mut<V: VisitorMut + ?Sized>(visitor: &mut V, in_: &In) {
+    visitor.visit_in_lhs_expr_mut(&in_.lhs);
+    visitor.visit_in_rhs_expr_mut(&in_.rhs);
+}
+
+/// The needle, `a` in `a in b`
+pub fn visit_in_lhs_expr_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &Expr) {
+    visitor.visit_expr_mut(expr);
+}
+
+/// The haystack, `b` in `a in b`
+pub fn visit_in_rhs_expr_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &Expr) {
+    visitor.visit_expr_mut(expr);
+}
+
+// The ancestry is only borrowed while a node is pushed or popped, so the visitor can borrow it in between
+fn enter_ancestor<V: Visitor + ?Sized>(visitor: &V, ancestor: impl FnOnce() -> Ancestor) {
+    if let Some(ancestry) = visitor.ancestry() {
+        ancestry.borrow_mut().push(ancestor());
+    }
+}
+
+fn exit_ancestor<V: Visitor + ?Sized>(visitor: &V) {
+    if let Some(ancestry) = visitor.ancestry() {
+        ancestry.borrow_mut().pop();
+    }
+}
+
+fn enter_ancestor_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    ancestor: impl FnOnce() -> Ancestor,
+) {
+    if let Some(ancestry) = visitor.ancestry_mut() {
+        ancestry.push(ancestor());
+    }
+}
+
+fn exit_ancestor_mut<V: VisitorMut + ?Sized>(visitor: &mut V) {
+    if let Some(ancestry) = visitor.ancestry_mut() {
+        ancestry.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use tera::Template;
+
+    use super::*;
+
+    /// Records the idents in the visit order
+    #[derive(Default)]
+    struct Idents(RefCell<Vec<String>>);
+
+    impl Visitor for Idents {
+        fn visit_ident_expr_val(&self, ident: &str) {
+            self.0.borrow_mut().push(ident.to_string());
+        }
+    }
+
+    impl VisitorMut for Vec<String> {
+        fn visit_ident_expr_val_mut(&mut self, ident: &str) {
+            self.push(ident.to_string());
+        }
+    }
+
+    fn idents(source: &str) -> Vec<String> {
+        let ast = Template::new("", None, source).unwrap().ast;
+        let visitor = Idents::default();
+        let mut visitor_mut = Vec::new();
+
+        visitor.visit_ast(&ast);
+        visitor_mut.visit_ast_mut(&ast);
+
+        assert_eq!(visitor.0.borrow().as_slice(), visitor_mut.as_slice());
+
+        visitor.0.into_inner()
+    }
+
+    #[test]
+    fn visits_math_and_logic_operands() {
+        assert_eq!(
+            idents("{{ a + b * c }}{% if d and not e or f > g %}{% endif %}"),
+            ["a", "b", "c", "d", "e", "f", "g"]
+        );
+    }
+
+    #[test]
+    fn visits_in_and_string_concat_operands() {
+        assert_eq!(
+            idents("{% if a in b %}{% elif c not in [d, 'x'] %}{% endif %}{{ e ~ 'x' ~ f }}"),
+            ["a", "b", "c", "d", "e", "f"]
+        );
+    }
+
+    #[test]
+    fn visits_arguments() {
+        assert_eq!(
+            idents(
+                "{{ a | default(value=b) }}{{ range(end=c) }}{{ m::n(x=d) }}\
+                 {% if e is containing(f) %}{% endif %}{% filter replace(from=g, to='') %}{% endfilter %}"
+            ),
+            // The filters come before the value, and the tested ident is a name, not an expression
+            ["b", "a", "c", "d", "f", "g"]
+        );
+    }
+
+    #[test]
+    fn visits_bodies() {
+        assert_eq!(
+            idents(
+                "{% macro m(n=1) %}{{ b }}{% endmacro %}{% block c %}{{ d }}{% endblock %}\
+                 {% for x in e %}{{ f }}{% else %}{{ g }}{% endfor %}{% set y = h %}"
+            ),
+            ["b", "d", "e", "f", "g", "h"]
+        );
+    }
+
+    /// Records the idents along with their ancestors
+    #[derive(Default)]
+    struct Ancestors {
+        ancestry: RefCell<Ancestry>,
+        idents: RefCell<Vec<(String, Vec<Ancestor>)>>,
+    }
+
+    impl Visitor for Ancestors {
+        fn ancestry(&self) -> Option<&RefCell<Ancestry>> {
+            Some(&self.ancestry)
+        }
+
+        fn visit_ident_expr_val(&self, ident: &str) {
+            let ancestors = self.ancestry.borrow().ancestors().to_vec();
+
+            self.idents
+                .borrow_mut()
+                .push((ident.to_string(), ancestors));
+        }
+    }
+
+    #[derive(Default)]
+    struct AncestorsMut {
+        ancestry: Ancestry,
+        idents: Vec<(String, Vec<Ancestor>)>,
+    }
+
+    impl VisitorMut for AncestorsMut {
+        fn ancestry_mut(&mut self) -> Option<&mut Ancestry> {
+            Some(&mut self.ancestry)
+        }
+
+        fn visit_ident_expr_val_mut(&mut self, ident: &str) {
+            let ancestors = self.ancestry.ancestors().to_vec();
+
+            self.idents.push((ident.to_string(), ancestors));
+        }
+    }
+
+    #[test]
+    fn tracks_the_ancestry() {
+        let ast = Template::new(
+            "",
+            None,
+            "{% block a %}{% for x in b %}{% if c %}{{ d }}{% else %}{{ e }}{% endif %}\
+             {% else %}{{ f }}{% endfor %}{% endblock %}{{ g }}",
+        )
+        .unwrap()
+        .ast;
+        let visitor = Ancestors::default();
+        let mut visitor_mut = AncestorsMut::default();
+
+        visitor.visit_ast(&ast);
+        visitor_mut.visit_ast_mut(&ast);
+
+        let block = Ancestor::Block {
+            name: "a".to_string(),
+        };
+        let forloop = Ancestor::Forloop {
+            key: None,
+            value: "x".to_string(),
+        };
+        let if_ = Ancestor::If { branch: 0 };
+
+        assert_eq!(
+            visitor.idents.borrow().as_slice(),
+            [
+                ("b".to_string(), vec![block.clone()]),
+                (
+                    "c".to_string(),
+                    vec![block.clone(), forloop.clone(), if_.clone()]
+                ),
+                ("d".to_string(), vec![block.clone(), forloop.clone(), if_]),
+                (
+                    "e".to_string(),
+                    vec![block.clone(), forloop, Ancestor::IfOtherwise]
+                ),
+                ("f".to_string(), vec![block, Ancestor::ForloopEmpty]),
+                ("g".to_string(), vec![]),
+            ]
+        );
+        assert_eq!(visitor.idents.into_inner(), visitor_mut.idents);
+        assert!(visitor.ancestry.borrow().is_empty());
+    }
 }
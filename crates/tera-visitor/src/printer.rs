@@ -0,0 +1,795 @@
+use std::{collections::HashMap, fmt, fmt::Write};
+
+use tera::{
+    ast::{
+        Block, Expr, ExprVal, FilterSection, Forloop, FunctionCall, If, In, LogicExpr,
+        LogicOperator, MacroCall, MacroDefinition, MathExpr, MathOperator, Set, StringConcat, Test,
+        WS,
+    },
+    Template,
+};
+
+use crate::{
+    source::{self, RegionKind},
+    Ast, VisitorMut,
+};
+
+/// Prints the AST back to a Tera source.
+///
+/// The AST doesn't keep the order of keyword arguments and macro parameters, without the source
+/// they are printed by name, use [`print_with_source`] to keep the order they were written in.
+#[must_use]
+pub fn print(ast: &Ast) -> String {
+    let mut printer = Printer::new();
+
+    printer.visit_ast_mut(ast);
+
+    printer.into_output()
+}
+
+/// Prints the AST parsed from `source` back to a Tera source,
+/// with the keyword arguments and macro parameters in the order of the source
+#[must_use]
+pub fn print_with_source(source: &str, ast: &Ast) -> String {
+    let mut printer = Printer::with_source(source);
+
+    printer.visit_ast_mut(ast);
+
+    printer.into_output()
+}
+
+/// Prints the AST parsed from `source` back to a Tera source,
+/// or fails if the output would lose something the source has
+///
+/// # Errors
+///
+/// What the output would lose, see [`Loss`]
+pub fn print_lossless(source: &str, ast: &Ast) -> Result<String, Loss> {
+    let regions = source::regions(source);
+
+    if regions
+        .iter()
+        .any(|region| region.kind == RegionKind::Comment)
+    {
+        return Err(Loss::Comments);
+    }
+
+    let trims_whitespace = regions.iter().any(|region| {
+        (region.trim_left() && source[..region.start].ends_with(char::is_whitespace))
+            || (region.trim_right() && source[region.end()..].starts_with(char::is_whitespace))
+    });
+
+    if trims_whitespace {
+        return Err(Loss::TrimmedWhitespace);
+    }
+
+    let mut printer = Printer::with_source(source);
+
+    printer.visit_ast_mut(ast);
+
+    if printer.unresolved_argument_order {
+        return Err(Loss::ArgumentOrder);
+    }
+
+    let output = printer.into_output();
+
+    if count_trim_markers(&output) != count_trim_markers(source) {
+        return Err(Loss::TrimMarkers);
+    }
+
+    let template = Template::new("", None, &output).map_err(|err| Loss::Parse(err.to_string()))?;
+
+    if template.ast != *ast {
+        return Err(Loss::RoundTrip);
+    }
+
+    Ok(output)
+}
+
+fn count_trim_markers(source: &str) -> usize {
+    source::regions(source)
+        .iter()
+        .map(|region| usize::from(region.trim_left()) + usize::from(region.trim_right()))
+        .sum()
+}
+
+/// What printing the AST would lose from the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Loss {
+    /// Tera drops the `{# #}` comments from the AST
+    Comments,
+    /// Tera removes the whitespace next to the `{%-` and `-%}` trim markers from the AST
+    TrimmedWhitespace,
+    /// Some trim markers are not kept in the AST, like the ones of the `else` tag of a `for` loop
+    TrimMarkers,
+    /// The order of some keyword arguments or macro parameters couldn't be found in the source
+    ArgumentOrder,
+    /// The output isn't a valid template
+    Parse(String),
+    /// The output is parsed to a different AST
+    RoundTrip,
+}
+
+impl fmt::Display for Loss {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Comments => write!(f, "the comments would be removed"),
+            Self::TrimmedWhitespace => {
+                write!(
+                    f,
+                    "the whitespace next to the trim markers would be removed"
+                )
+            }
+            Self::TrimMarkers => write!(f, "some trim markers would be removed"),
+            Self::ArgumentOrder => write!(f, "the order of some arguments would change"),
+            Self::Parse(err) => write!(f, "the output wouldn't be a valid template: {err}"),
+            Self::RoundTrip => write!(f, "the output wouldn't be parsed to the same template"),
+        }
+    }
+}
+
+impl std::error::Error for Loss {}
+
+#[derive(Debug, Default)]
+pub struct Printer {
+    output: String,
+    /// The keyword argument names of the calls in the source, see [`source::argument_orders`]
+    argument_orders: Vec<Vec<String>>,
+    next_argument_order: usize,
+    unresolved_argument_order: bool,
+}
+
+impl Printer {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A printer keeping the order of the keyword arguments and macro parameters of `source`
+    #[must_use]
+    pub fn with_source(source: &str) -> Self {
+        Self {
+            argument_orders: source::argument_orders(source),
+            ..Self::default()
+        }
+    }
+
+    /// Whether some arguments were printed by name, since their order wasn't found in the source
+    #[must_use]
+    pub fn unresolved_argument_order(&self) -> bool {
+        self.unresolved_argument_order
+    }
+
+    #[must_use]
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    #[must_use]
+    pub fn into_output(self) -> String {
+        self.output
+    }
+
+    fn push_tag_start(&mut self, ws: WS) {
+        self.output.push_str(if ws.left { "{%- " } else { "{% " });
+    }
+
+    fn push_tag_end(&mut self, ws: WS) {
+        self.output.push_str(if ws.right { " -%}" } else { " %}" });
+    }
+
+    /// A whole `{% content %}` tag
+    fn push_tag(&mut self, ws: WS, content: &str) {
+        self.push_tag_start(ws);
+        self.output.push_str(content);
+        self.push_tag_end(ws);
+    }
+
+    /// Tera strings can't be escaped, so the first quote not used in the string is picked
+    fn push_string(&mut self, s: &str) {
+        let quote = ['"', '\'', '`']
+            .into_iter()
+            .find(|quote| !s.contains(*quote))
+            .unwrap_or('"');
+
+        self.output.push(quote);
+        self.output.push_str(s);
+        self.output.push(quote);
+    }
+
+    /// The arguments in the order of the source. The calls are printed in the order they are written,
+    /// so the order is the next one in the source with the same names, or by name if there is none.
+    fn ordered_args<'b, T>(&mut self, args: &'b HashMap<String, T>) -> Vec<(&'b String, &'b T)> {
+        let found = (args.len() >= 2)
+            .then(|| {
+                self.argument_orders[self.next_argument_order..]
+                    .iter()
+                    .position(|names| {
+                        names.len() == args.len()
+                            && names.iter().all(|name| args.contains_key(name))
+                    })
+            })
+            .flatten();
+
+        if let Some(offset) = found {
+            let index = self.next_argument_order + offset;
+
+            self.next_argument_order = index + 1;
+
+            return self.argument_orders[index]
+                .iter()
+                .filter_map(|name| args.get_key_value(name))
+                .collect();
+        }
+
+        if args.len() >= 2 {
+            self.unresolved_argument_order = true;
+        }
+
+        let mut args = args.iter().collect::<Vec<_>>();
+
+        args.sort_by_key(|(name, _)| *name);
+
+        args
+    }
+
+    fn push_kwargs(&mut self, args: &HashMap<String, Expr>) {
+        let args = self.ordered_args(args);
+
+        for (index, (name, expr)) in args.into_iter().enumerate() {
+            if index > 0 {
+                self.output.push_str(", ");
+            }
+
+            self.output.push_str(name);
+            self.output.push('=');
+            self.visit_expr_mut(expr);
+        }
+    }
+
+    /// Filters without arguments are printed without parentheses, `| upper`
+    fn push_filter(&mut self, function_call: &FunctionCall) {
+        if function_call.args.is_empty() {
+            self.output.push_str(&function_call.name);
+        } else {
+            self.visit_function_call_mut(function_call);
+        }
+    }
+
+    /// Operands of a math expression are wrapped in parentheses when the precedence requires it
+    fn push_math_operand(&mut self, expr: &Expr, operator: MathOperator, is_rhs: bool) {
+        let needs_parentheses = match &expr.val {
+            ExprVal::Math(operand) => {
+                let operand_precedence = math_operator_precedence(operand.operator);
+                let precedence = math_operator_precedence(operator);
+
+                operand_precedence < precedence || (is_rhs && operand_precedence == precedence)
+            }
+            _ => false,
+        };
+
+        if needs_parentheses {
+            self.output.push('(');
+        }
+
+        if is_rhs {
+            self.visit_math_rhs_expr_mut(expr);
+        } else {
+            self.visit_math_lhs_expr_mut(expr);
+        }
+
+        if needs_parentheses {
+            self.output.push(')');
+        }
+    }
+
+    /// Tera has no parentheses in logic expressions, they are only needed by the expressions
+    /// rebuilt by a [`Fold`](crate::Fold), which then fail to parse rather than change meaning
+    fn push_logic_operand(&mut self, expr: &Expr, operator: LogicOperator, is_rhs: bool) {
+        let needs_parentheses = match &expr.val {
+            ExprVal::Logic(operand) if !expr.negated => {
+                let operand_precedence = logic_operator_precedence(operand.operator);
+                let precedence = logic_operator_precedence(operator);
+
+                operand_precedence < precedence
+                    || (operand_precedence == precedence && (is_rhs || precedence == 2))
+            }
+            _ => false,
+        };
+
+        if needs_parentheses {
+            self.output.push('(');
+        }
+
+        if is_rhs {
+            self.visit_logic_rhs_expr_mut(expr);
+        } else {
+            self.visit_logic_lhs_expr_mut(expr);
+        }
+
+        if needs_parentheses {
+            self.output.push(')');
+        }
+    }
+}
+
+fn logic_operator_precedence(operator: LogicOperator) -> u8 {
+    match operator {
+        LogicOperator::Or => 0,
+        LogicOperator::And => 1,
+        _ => 2,
+    }
+}
+
+fn math_operator_precedence(operator: MathOperator) -> u8 {
+    match operator {
+        MathOperator::Add | MathOperator::Sub => 0,
+        MathOperator::Mul | MathOperator::Div | MathOperator::Modulo => 1,
+    }
+}
+
+impl VisitorMut for Printer {
+    fn visit_super_mut(&mut self) {
+        self.output.push_str("{{ super() }}");
+    }
+
+    fn visit_text_mut(&mut self, text: &str) {
+        self.output.push_str(text);
+    }
+
+    fn visit_variable_block_mut(&mut self, ws: &WS, expr: &Expr) {
+        self.output.push_str(if ws.left { "{{- " } else { "{{ " });
+        self.visit_expr_mut(expr);
+        self.output.push_str(if ws.right { " -}}" } else { " }}" });
+    }
+
+    fn visit_macro_definition_mut(
+        &mut self,
+        left_ws: &WS,
+        macro_definition: &MacroDefinition,
+        right_ws: &WS,
+    ) {
+        let args = self.ordered_args(&macro_definition.args);
+
+        self.push_tag_start(*left_ws);
+        write!(self.output, "macro {}(", macro_definition.name).unwrap();
+
+        for (index, (name, default)) in args.into_iter().enumerate() {
+            if index > 0 {
+                self.output.push_str(", ");
+            }
+
+            self.output.push_str(name);
+
+            if let Some(default) = default {
+                self.output.push('=');
+                self.visit_expr_mut(default);
+            }
+        }
+
+        self.output.push(')');
+        self.push_tag_end(*left_ws);
+        self.visit_ast_mut(&macro_definition.body);
+        self.push_tag(*right_ws, &format!("endmacro {}", macro_definition.name));
+    }
+
+    fn visit_extends_mut(&mut self, ws: &WS, content: &str) {
+        self.push_tag_start(*ws);
+        self.output.push_str("extends ");
+        self.push_string(content);
+        self.push_tag_end(*ws);
+    }
+
+    fn visit_include_mut(&mut self, ws: &WS, includes: &[String], ignore_missing: bool) {
+        self.push_tag_start(*ws);
+        self.output.push_str("include ");
+
+        if let [include] = includes {
+            self.push_string(include);
+        } else {
+            self.output.push('[');
+
+            for (index, include) in includes.iter().enumerate() {
+                if index > 0 {
+                    self.output.push_str(", ");
+                }
+
+                self.push_string(include);
+            }
+
+            self.output.push(']');
+        }
+
+        if ignore_missing {
+            self.output.push_str(" ignore missing");
+        }
+
+        self.push_tag_end(*ws);
+    }
+
+    fn visit_import_macro_mut(&mut self, ws: &WS, path: &str, name: &str) {
+        self.push_tag_start(*ws);
+        self.output.push_str("import ");
+        self.push_string(path);
+        write!(self.output, " as {name}").unwrap();
+        self.push_tag_end(*ws);
+    }
+
+    fn visit_set_mut(&mut self, ws: &WS, set: &Set) {
+        self.push_tag_start(*ws);
+        let tag = if set.global { "set_global" } else { "set" };
+        write!(self.output, "{tag} {} = ", set.key).unwrap();
+        self.visit_expr_mut(&set.value);
+        self.push_tag_end(*ws);
+    }
+
+    fn visit_raw_mut(&mut self, left_ws: &WS, content: &str, right_ws: &WS) {
+        self.push_tag(*left_ws, "raw");
+        self.output.push_str(content);
+        self.push_tag(*right_ws, "endraw");
+    }
+
+    fn visit_filter_section_mut(
+        &mut self,
+        left_ws: &WS,
+        filter_section: &FilterSection,
+        right_ws: &WS,
+    ) {
+        self.push_tag_start(*left_ws);
+        self.output.push_str("filter ");
+        self.push_filter(&filter_section.filter);
+        self.push_tag_end(*left_ws);
+        self.visit_ast_mut(&filter_section.body);
+        self.push_tag(*right_ws, "endfilter");
+    }
+
+    fn visit_block_mut(&mut self, left_ws: &WS, block: &Block, right_ws: &WS) {
+        self.push_tag(*left_ws, &format!("block {}", block.name));
+        self.visit_ast_mut(&block.body);
+        self.push_tag(*right_ws, &format!("endblock {}", block.name));
+    }
+
+    fn visit_forloop_mut(&mut self, left_ws: &WS, forloop: &Forloop, right_ws: &WS) {
+        self.push_tag_start(*left_ws);
+        self.output.push_str("for ");
+
+        if let Some(key) = &forloop.key {
+            write!(self.output, "{key}, ").unwrap();
+        }
+
+        write!(self.output, "{} in ", forloop.value).unwrap();
+        self.visit_forloop_expr_mut(&forloop.container);
+        self.push_tag_end(*left_ws);
+        self.visit_ast_mut(&forloop.body);
+
+        if let Some(empty_body) = &forloop.empty_body {
+            // The whitespace of the `else` tag is not kept by Tera
+            self.push_tag(WS::default(), "else");
+            self.visit_ast_mut(empty_body);
+        }
+
+        self.push_tag(*right_ws, "endfor");
+    }
+
+    fn visit_if_mut(&mut self, if_: &If, ws: &WS) {
+        for (index, (condition_ws, expr, ast)) in if_.conditions.iter().enumerate() {
+            self.push_tag_start(*condition_ws);
+
+            if index == 0 {
+                self.output.push_str("if ");
+                self.visit_if_expr_mut(expr);
+            } else {
+                self.output.push_str("elif ");
+                self.visit_else_if_expr_mut(expr);
+            }
+
+            self.push_tag_end(*condition_ws);
+            self.visit_ast_mut(ast);
+        }
+
+        if let Some((otherwise_ws, ast)) = &if_.otherwise {
+            self.push_tag(*otherwise_ws, "else");
+            self.visit_ast_mut(ast);
+        }
+
+        self.push_tag(*ws, "endif");
+    }
+
+    fn visit_break_mut(&mut self, ws: &WS) {
+        self.push_tag(*ws, "break");
+    }
+
+    fn visit_continue_mut(&mut self, ws: &WS) {
+        self.push_tag(*ws, "continue");
+    }
+
+    fn visit_comment_mut(&mut self, ws: &WS, content: &str) {
+        self.output.push_str(if ws.left { "{#-" } else { "{#" });
+        self.output.push_str(content);
+        self.output.push_str(if ws.right { "-#}" } else { "#}" });
+    }
+
+    fn visit_expr_mut(&mut self, expr: &Expr) {
+        // Tera only negates single conditions, a negated `and` or `or` comes from a `Fold`
+        let negates_composite = expr.negated
+            && matches!(
+                &expr.val,
+                ExprVal::Logic(LogicExpr {
+                    operator: LogicOperator::And | LogicOperator::Or,
+                    ..
+                })
+            );
+
+        if negates_composite {
+            self.output.push_str("not (");
+        } else if expr.negated {
+            self.output.push_str("not ");
+        }
+
+        self.visit_expr_val_mut(&expr.val);
+
+        if negates_composite {
+            self.output.push(')');
+        }
+
+        for function_call in &expr.filters {
+            self.output.push_str(" | ");
+            self.push_filter(function_call);
+        }
+    }
+
+    fn visit_string_expr_val_mut(&mut self, s: &str) {
+        self.push_string(s);
+    }
+
+    fn visit_int_expr_val_mut(&mut self, i: i64) {
+        write!(self.output, "{i}").unwrap();
+    }
+
+    fn visit_float_expr_val_mut(&mut self, f: f64) {
+        // Debug formatting always keeps the decimal point
+        write!(self.output, "{f:?}").unwrap();
+    }
+
+    fn visit_bool_expr_val_mut(&mut self, b: bool) {
+        write!(self.output, "{b}").unwrap();
+    }
+
+    fn visit_ident_expr_val_mut(&mut self, ident: &str) {
+        self.output.push_str(ident);
+    }
+
+    fn visit_math_expr_val_mut(&mut self, math_expr: &MathExpr) {
+        self.push_math_operand(&math_expr.lhs, math_expr.operator, false);
+        write!(self.output, " {} ", math_expr.operator).unwrap();
+        self.push_math_operand(&math_expr.rhs, math_expr.operator, true);
+    }
+
+    fn visit_logic_expr_val_mut(&mut self, logic_expr: &LogicExpr) {
+        self.push_logic_operand(&logic_expr.lhs, logic_expr.operator, false);
+        write!(self.output, " {} ", logic_expr.operator).unwrap();
+        self.push_logic_operand(&logic_expr.rhs, logic_expr.operator, true);
+    }
+
+    fn visit_test_expr_val_mut(&mut self, test: &Test) {
+        write!(self.output, "{} is ", test.ident).unwrap();
+
+        if test.negated {
+            self.output.push_str("not ");
+        }
+
+        self.output.push_str(&test.name);
+
+        if !test.args.is_empty() {
+            self.output.push('(');
+
+            for (index, expr) in test.args.iter().enumerate() {
+                if index > 0 {
+                    self.output.push_str(", ");
+                }
+
+                self.visit_expr_mut(expr);
+            }
+
+            self.output.push(')');
+        }
+    }
+
+    fn visit_array_expr_val_mut(&mut self, array: &Vec<Expr>) {
+        self.output.push('[');
+
+        for (index, expr) in array.iter().enumerate() {
+            if index > 0 {
+                self.output.push_str(", ");
+            }
+
+            self.visit_expr_mut(expr);
+        }
+
+        self.output.push(']');
+    }
+
+    fn visit_string_concat_expr_val_mut(&mut self, string_concat: &StringConcat) {
+        for (index, expr_val) in string_concat.values.iter().enumerate() {
+            if index > 0 {
+                self.output.push_str(" ~ ");
+            }
+
+            self.visit_string_concat_value_mut(expr_val);
+        }
+    }
+
+    fn visit_macro_call_mut(&mut self, macro_call: &MacroCall) {
+        write!(
+            self.output,
+            "{}::{}(",
+            macro_call.namespace, macro_call.name
+        )
+        .unwrap();
+        self.push_kwargs(&macro_call.args);
+        self.output.push(')');
+    }
+
+    fn visit_function_call_mut(&mut self, function_call: &FunctionCall) {
+        write!(self.output, "{}(", function_call.name).unwrap();
+        self.push_kwargs(&function_call.args);
+        self.output.push(')');
+    }
+
+    fn visit_in_expr_val_mut(&mut self, in_: &In) {
+        self.visit_in_lhs_expr_mut(&in_.lhs);
+        self.output
+            .push_str(if in_.negated { " not in " } else { " in " });
+        self.visit_in_rhs_expr_mut(&in_.rhs);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tera::{ast::Node, Context, Tera};
+
+    use super::*;
+
+    fn parse(source: &str) -> Ast {
+        Template::new("", None, source).unwrap().ast
+    }
+
+    fn render(source: &str) -> String {
+        let mut context = Context::new();
+
+        context.insert("name", "Ada");
+        context.insert("jobs", &["a", "b"]);
+        context.insert("skills", &HashMap::from([("rust", 3), ("tera", 2)]));
+        context.insert("count", &3);
+
+        let mut tera = Tera::default();
+
+        tera.add_raw_templates([("macros.html", MACROS), ("template.html", source)])
+            .unwrap();
+        tera.render("template.html", &context).unwrap()
+    }
+
+    const MACROS: &str = "{% macro m(n, d=1) %}{{ n }}{{ d }}{% endmacro m %}";
+
+    #[test]
+    fn round_trips_macro_definitions() {
+        let ast = parse(MACROS);
+
+        assert_eq!(print_lossless(MACROS, &ast).unwrap(), MACROS);
+    }
+
+    #[test]
+    fn round_trips() {
+        let sources = [
+            "Hello {{ name | upper }}!",
+            "{% if name and count > 2 or not jobs %}a{% elif count is odd %}b{% else %}c{% endif %}",
+            "{% for job in jobs | reverse %}{{ loop.index }}{{ job }}{% else %}none{% endfor %}",
+            "{% for key, value in skills %}{{ key }}={{ value }}{% endfor %}",
+            "{% set total = (count + 1) * 2 - count % 2 %}{{ total }}",
+            "{{ range(end=6, start=1, step_by=2) | join(sep=', ') }}",
+            "{% import 'macros.html' as macros %}{{ macros::m(d=3, n=2) }}{{ macros::m(n=4) }}",
+            "{% raw %}{{ not parsed }}{% endraw %}",
+            "{{- name -}}{% if 'a' ~ \"b\" in name %}x{% endif %}",
+            "{% filter upper %}{{ name }}{% endfilter %}",
+        ];
+
+        for source in sources {
+            let ast = parse(source);
+            let output = print_lossless(source, &ast).unwrap();
+
+            assert_eq!(parse(&output), ast, "{output}");
+            assert_eq!(render(&output), render(source), "{output}");
+        }
+    }
+
+    #[test]
+    fn keeps_the_argument_order() {
+        let source = "{% macro m(n, d=1) %}{% endmacro m %}{{ f(z=1, a=2) }}{{ f(a=1, z=2) }}";
+
+        assert_eq!(
+            print_lossless(source, &parse(source)).unwrap(),
+            "{% macro m(n, d=1) %}{% endmacro m %}{{ f(z=1, a=2) }}{{ f(a=1, z=2) }}"
+        );
+    }
+
+    #[test]
+    fn argument_order_without_source_is_unresolved() {
+        let source = "{{ f(z=1, a=2) }}";
+        let mut printer = Printer::new();
+
+        printer.visit_ast_mut(&parse(source));
+
+        assert!(printer.unresolved_argument_order());
+        assert_eq!(printer.output(), "{{ f(a=2, z=1) }}");
+    }
+
+    #[test]
+    fn refuses_lossy_output() {
+        let cases = [
+            ("{# c #}x", Loss::Comments),
+            ("a  {{- name }}", Loss::TrimmedWhitespace),
+            ("{{ name -}}\n", Loss::TrimmedWhitespace),
+            (
+                "{% for i in jobs %}a{%- else -%}b{% endfor %}",
+                Loss::TrimMarkers,
+            ),
+        ];
+
+        for (source, loss) in cases {
+            assert_eq!(
+                print_lossless(source, &parse(source)),
+                Err(loss),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn refuses_rebuilt_ast_that_does_not_round_trip() {
+        let source = "{{ a or b }}";
+        let mut ast = parse(source);
+
+        if let Node::VariableBlock(_, expr) = &mut ast[0] {
+            expr.negated = true;
+        }
+
+        assert!(matches!(print_lossless(source, &ast), Err(Loss::Parse(_))));
+    }
+
+    fn ident(name: &str) -> Expr {
+        Expr::new(ExprVal::Ident(name.to_string()))
+    }
+
+    fn logic(lhs: Expr, operator: LogicOperator, rhs: Expr) -> Expr {
+        Expr::new(ExprVal::Logic(LogicExpr {
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+            operator,
+        }))
+    }
+
+    #[test]
+    fn parenthesises_rebuilt_logic_expressions() {
+        let or = logic(ident("a"), LogicOperator::Or, ident("b"));
+        let and = logic(or.clone(), LogicOperator::And, ident("c"));
+        let mut negated = or;
+
+        negated.negated = true;
+
+        assert_eq!(
+            print(&vec![Node::VariableBlock(WS::default(), and)]),
+            "{{ (a or b) and c }}"
+        );
+        assert_eq!(
+            print(&vec![Node::VariableBlock(WS::default(), negated)]),
+            "{{ not (a or b) }}"
+        );
+    }
+
+    #[test]
+    fn keeps_math_precedence() {
+        let source = "{{ (1 + 2) * 3 - (4 - 5) }}";
+
+        assert_eq!(print(&parse(source)), source);
+    }
+}
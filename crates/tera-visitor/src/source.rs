@@ -0,0 +1,416 @@
+//! Splits a Tera source into text, tags and comments, without parsing the expressions.
+//!
+//! The Tera AST loses the comments, the whitespace removed by the trim markers,
+//! and the order of keyword arguments, the source is the only place they can be found.
+
+/// What a region of the source contains
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegionKind {
+    /// Some text outside of any tag
+    Text,
+    /// A `{{ }}` block
+    Variable,
+    /// A `{% %}` tag
+    Tag,
+    /// A `{# #}` comment
+    Comment,
+    /// The content between `{% raw %}` and `{% endraw %}`, which is never parsed by Tera
+    Raw,
+}
+
+/// A region of the source, tags and comments are kept with their delimiters
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Region<'a> {
+    pub kind: RegionKind,
+    /// Byte offset of the region in the source
+    pub start: usize,
+    pub text: &'a str,
+}
+
+impl<'a> Region<'a> {
+    /// Byte offset of the end of the region in the source
+    #[must_use]
+    pub fn end(&self) -> usize {
+        self.start + self.text.len()
+    }
+
+    #[must_use]
+    pub fn is_delimited(&self) -> bool {
+        matches!(
+            self.kind,
+            RegionKind::Variable | RegionKind::Tag | RegionKind::Comment
+        )
+    }
+
+    /// Whether the region starts with a `{{-`, `{%-` or `{#-` trim marker
+    #[must_use]
+    pub fn trim_left(&self) -> bool {
+        self.is_delimited() && self.text[2..].starts_with('-')
+    }
+
+    /// Whether the region ends with a `-}}`, `-%}` or `-#}` trim marker
+    #[must_use]
+    pub fn trim_right(&self) -> bool {
+        self.is_delimited()
+            && self.text.len() >= 5 + usize::from(self.trim_left())
+            && self.text.ends_with(closing_delimiter(self.kind))
+            && self.text[..self.text.len() - 2].ends_with('-')
+    }
+
+    /// The content between the delimiters and the trim markers, without the surrounding whitespace
+    #[must_use]
+    pub fn content(&self) -> &'a str {
+        if !self.is_delimited() {
+            return self.text;
+        }
+
+        let mut content = &self.text[2..];
+
+        content = content
+            .strip_suffix(closing_delimiter(self.kind))
+            .unwrap_or(content);
+
+        if self.trim_left() {
+            content = &content[1..];
+        }
+
+        if self.trim_right() {
+            content = &content[..content.len() - 1];
+        }
+
+        content.trim()
+    }
+}
+
+fn closing_delimiter(kind: RegionKind) -> &'static str {
+    match kind {
+        RegionKind::Variable => "}}",
+        RegionKind::Comment => "#}",
+        _ => "%}",
+    }
+}
+
+/// Splits the source into regions, an unclosed tag or comment extends to the end of the source
+#[must_use]
+pub fn regions(source: &str) -> Vec<Region> {
+    let mut regions = Vec::new();
+    let mut text_start = 0;
+    let mut position = 0;
+
+    while let Some(offset) = find_opening(&source[position..]) {
+        let start = position + offset;
+        let kind = match &source[start + 1..start + 2] {
+            "{" => RegionKind::Variable,
+            "%" => RegionKind::Tag,
+            _ => RegionKind::Comment,
+        };
+        let end = find_closing(source, start + 2, kind);
+
+        push_region(&mut regions, source, RegionKind::Text, text_start, start);
+        push_region(&mut regions, source, kind, start, end);
+        position = end;
+        text_start = end;
+
+        let is_raw = regions.last().map_or(false, |region| {
+            kind == RegionKind::Tag && region.content() == "raw"
+        });
+
+        if is_raw {
+            let raw_end = find_endraw(source, end).unwrap_or(source.len());
+
+            push_region(&mut regions, source, RegionKind::Raw, end, raw_end);
+            position = raw_end;
+            text_start = raw_end;
+        }
+    }
+
+    push_region(
+        &mut regions,
+        source,
+        RegionKind::Text,
+        text_start,
+        source.len(),
+    );
+
+    regions
+}
+
+fn push_region<'a>(
+    regions: &mut Vec<Region<'a>>,
+    source: &'a str,
+    kind: RegionKind,
+    start: usize,
+    end: usize,
+) {
+    if start < end {
+        regions.push(Region {
+            kind,
+            start,
+            text: &source[start..end],
+        });
+    }
+}
+
+fn find_opening(source: &str) -> Option<usize> {
+    source
+        .match_indices('{')
+        .map(|(index, _)| index)
+        .find(|index| matches!(source.as_bytes().get(index + 1), Some(b'{' | b'%' | b'#')))
+}
+
+/// The end of a tag, quoted strings are skipped since they can contain the closing delimiter
+fn find_closing(source: &str, from: usize, kind: RegionKind) -> usize {
+    let closing = closing_delimiter(kind);
+    let mut quote = None;
+    let mut index = from;
+
+    while index < source.len() {
+        let rest = &source[index..];
+        let c = rest.chars().next().unwrap_or_default();
+
+        match quote {
+            Some(q) if c == q => quote = None,
+            None if kind != RegionKind::Comment && matches!(c, '"' | '\'' | '`') => {
+                quote = Some(c);
+            }
+            None if rest.starts_with(closing) => return index + closing.len(),
+            _ => {}
+        }
+
+        index += c.len_utf8();
+    }
+
+    source.len()
+}
+
+/// The start of the `{% endraw %}` tag following a `{% raw %}` tag
+fn find_endraw(source: &str, from: usize) -> Option<usize> {
+    source[from..]
+        .match_indices("{%")
+        .map(|(index, _)| from + index)
+        .find(|start| {
+            let end = find_closing(source, start + 2, RegionKind::Tag);
+            let region = Region {
+                kind: RegionKind::Tag,
+                start: *start,
+                text: &source[*start..end],
+            };
+
+            region.content() == "endraw"
+        })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Token<'a> {
+    Ident(&'a str),
+    Assign,
+    Open(char),
+    Close,
+    Comma,
+    Other,
+}
+
+fn tokenize(content: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = content.char_indices().peekable();
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            c if c.is_whitespace() => continue,
+            '"' | '\'' | '`' => {
+                for (_, next) in chars.by_ref() {
+                    if next == c {
+                        break;
+                    }
+                }
+
+                tokens.push(Token::Other);
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut end = index + c.len_utf8();
+
+                while let Some((next_index, next)) = chars.peek() {
+                    if next.is_ascii_alphanumeric() || *next == '_' {
+                        end = next_index + next.len_utf8();
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+
+                tokens.push(Token::Ident(&content[index..end]));
+            }
+            '=' | '!' | '<' | '>' => {
+                if chars.peek().map_or(false, |(_, next)| *next == '=') {
+                    chars.next();
+                    tokens.push(Token::Other);
+                } else if c == '=' {
+                    tokens.push(Token::Assign);
+                } else {
+                    tokens.push(Token::Other);
+                }
+            }
+            '(' | '[' => tokens.push(Token::Open(c)),
+            ')' | ']' => tokens.push(Token::Close),
+            ',' => tokens.push(Token::Comma),
+            _ => tokens.push(Token::Other),
+        }
+    }
+
+    tokens
+}
+
+/// The names of the keyword arguments of each call, and of the parameters of each macro definition,
+/// in the order the calls start in the source. Only the calls with at least two names are listed,
+/// since Tera keeps the arguments in a `HashMap` and the order of a single one can't be lost.
+#[must_use]
+pub fn argument_orders(source: &str) -> Vec<Vec<String>> {
+    let mut orders = Vec::new();
+
+    for region in regions(source) {
+        if !matches!(region.kind, RegionKind::Variable | RegionKind::Tag) {
+            continue;
+        }
+
+        let tokens = tokenize(region.content());
+        let is_macro_definition =
+            region.kind == RegionKind::Tag && tokens.first() == Some(&Token::Ident("macro"));
+        // The index in `orders` of each open parenthesis, `None` for brackets and grouping parentheses
+        let mut stack: Vec<Option<usize>> = Vec::new();
+
+        for (index, token) in tokens.iter().enumerate() {
+            let next = tokens.get(index + 1);
+
+            match token {
+                Token::Open('(')
+                    if matches!(
+                        index.checked_sub(1).map(|i| tokens[i]),
+                        Some(Token::Ident(_))
+                    ) =>
+                {
+                    stack.push(Some(orders.len()));
+                    orders.push(Vec::new());
+                }
+                Token::Open(_) => stack.push(None),
+                Token::Close => {
+                    stack.pop();
+                }
+                Token::Ident(name) => {
+                    let Some(Some(order)) = stack.last() else {
+                        continue;
+                    };
+                    let is_parameter = is_macro_definition
+                        && stack.len() == 1
+                        && matches!(next, Some(Token::Comma | Token::Close));
+
+                    if next == Some(&Token::Assign) || is_parameter {
+                        orders[*order].push((*name).to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    orders.retain(|order| order.len() >= 2);
+
+    orders
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<(RegionKind, &str)> {
+        regions(source)
+            .into_iter()
+            .map(|region| (region.kind, region.text))
+            .collect()
+    }
+
+    #[test]
+    fn splits_text_tags_and_comments() {
+        assert_eq!(
+            kinds("a{{ x }}b{% if y %}{# c #}{% endif %}"),
+            vec![
+                (RegionKind::Text, "a"),
+                (RegionKind::Variable, "{{ x }}"),
+                (RegionKind::Text, "b"),
+                (RegionKind::Tag, "{% if y %}"),
+                (RegionKind::Comment, "{# c #}"),
+                (RegionKind::Tag, "{% endif %}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_delimiters_in_strings() {
+        assert_eq!(
+            kinds(r#"{{ "}}" ~ '%}' }}x"#),
+            vec![
+                (RegionKind::Variable, r#"{{ "}}" ~ '%}' }}"#),
+                (RegionKind::Text, "x"),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_raw_content_unparsed() {
+        assert_eq!(
+            kinds("{% raw -%}{{ x }}{# y #}{%- endraw %}"),
+            vec![
+                (RegionKind::Tag, "{% raw -%}"),
+                (RegionKind::Raw, "{{ x }}{# y #}"),
+                (RegionKind::Tag, "{%- endraw %}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_tag_extends_to_the_end() {
+        assert_eq!(
+            kinds("a{{ x"),
+            vec![(RegionKind::Text, "a"), (RegionKind::Variable, "{{ x")]
+        );
+    }
+
+    #[test]
+    fn reads_trim_markers() {
+        let regions = regions("{{- x }}{% y -%}{#-z-#}{{-}}");
+
+        assert_eq!(
+            regions
+                .iter()
+                .map(|region| (region.trim_left(), region.trim_right(), region.content()))
+                .collect::<Vec<_>>(),
+            vec![
+                (true, false, "x"),
+                (false, true, "y"),
+                (true, true, "z"),
+                (true, false, ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_the_order_of_keyword_arguments() {
+        assert_eq!(
+            argument_orders(
+                "{{ f(b=1, a=g(y=2, x=[1, 2]), c=a == b) }}{% macro m(n, d=1) %}{% endmacro %}"
+            ),
+            vec![
+                vec!["b".to_string(), "a".to_string(), "c".to_string()],
+                vec!["y".to_string(), "x".to_string()],
+                vec!["n".to_string(), "d".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_set_and_single_arguments() {
+        assert!(argument_orders(
+            "{% set x = f(a=1) %}{% for k, v in m::c(z=1) %}{% endfor %}{{ (a + b) * c }}"
+        )
+        .is_empty());
+    }
+}
@@ -0,0 +1,1629 @@
+#![allow(clippy::module_name_repetitions)]
+
+use std::{cell::RefCell, ops::ControlFlow};
+
+use tera::ast::{
+    Block, Expr, ExprVal, FilterSection, Forloop, FunctionCall, If, In, LogicExpr, MacroCall,
+    MacroDefinition, MathExpr, Node, Set, StringConcat, Test, WS,
+};
+
+use crate::{Ancestor, Ancestry, Ast};
+
+/// Same as [`crate::Visitor`], but any method can stop the visit early by returning [`ControlFlow::Break`]
+pub trait TryVisitor {
+    type Break;
+
+    /// The ancestors of the visited node, kept up to date by the walkers if some storage is returned
+    fn ancestry(&self) -> Option<&RefCell<Ancestry>> {
+        None
+    }
+
+    // Whole AST
+    fn try_visit_ast(&self, ast: &Ast) -> ControlFlow<Self::Break> {
+        try_visit_ast(self, ast)
+    }
+
+    /// A call to `{{ super() }}` in a block
+    fn try_visit_super(&self) -> ControlFlow<Self::Break> {
+        try_visit_super(self)
+    }
+
+    /// Some actual text
+    fn try_visit_text(&self, text: &str) -> ControlFlow<Self::Break> {
+        try_visit_text(self, text)
+    }
+
+    /// A `{{ }}` block
+    fn try_visit_variable_block(&self, ws: &WS, expr: &Expr) -> ControlFlow<Self::Break> {
+        try_visit_variable_block(self, ws, expr)
+    }
+
+    /// A `{% macro hello() %}...{% endmacro %}`
+    fn try_visit_macro_definition(
+        &self,
+        left_ws: &WS,
+        macro_definition: &MacroDefinition,
+        right_ws: &WS,
+    ) -> ControlFlow<Self::Break> {
+        try_visit_macro_definition(self, left_ws, macro_definition, right_ws)
+    }
+
+    /// The `{% extends "blabla.html" %}` node, contains the template name
+    fn try_visit_extends(&self, ws: &WS, content: &str) -> ControlFlow<Self::Break> {
+        try_visit_extends(self, ws, content)
+    }
+
+    /// The `{% include "blabla.html" %}` node, contains the template name
+    fn try_visit_include(
+        &self,
+        ws: &WS,
+        includes: &[String],
+        ignore_missing: bool,
+    ) -> ControlFlow<Self::Break> {
+        try_visit_include(self, ws, includes, ignore_missing)
+    }
+
+    /// The `{% import "macros.html" as macros %}`
+    fn try_visit_import_macro(&self, ws: &WS, path: &str, name: &str) -> ControlFlow<Self::Break> {
+        try_visit_import_macro(self, ws, path, name)
+    }
+
+    /// The `{% set val = something %}` tag
+    fn try_visit_set(&self, ws: &WS, set: &Set) -> ControlFlow<Self::Break> {
+        try_visit_set(self, ws, set)
+    }
+
+    /// The text between `{% raw %}` and `{% endraw %}`
+    fn try_visit_raw(
+        &self,
+        left_ws: &WS,
+        content: &str,
+        right_ws: &WS,
+    ) -> ControlFlow<Self::Break> {
+        try_visit_raw(self, left_ws, content, right_ws)
+    }
+
+    /// A filter section node `{{ filter name(param="value") }} content {{ endfilter }}`
+    fn try_visit_filter_section(
+        &self,
+        left_ws: &WS,
+        filter_section: &FilterSection,
+        right_ws: &WS,
+    ) -> ControlFlow<Self::Break> {
+        try_visit_filter_section(self, left_ws, filter_section, right_ws)
+    }
+
+    /// A `{% block name %}...{% endblock %}`
+    fn try_visit_block(
+        &self,
+        left_ws: &WS,
+        block: &Block,
+        right_ws: &WS,
+    ) -> ControlFlow<Self::Break> {
+        try_visit_block(self, left_ws, block, right_ws)
+    }
+
+    /// A `{% for i in items %}...{% endfor %}`
+    fn try_visit_forloop(
+        &self,
+        left_ws: &WS,
+        forloop: &Forloop,
+        right_ws: &WS,
+    ) -> ControlFlow<Self::Break> {
+        try_visit_forloop(self, left_ws, forloop, right_ws)
+    }
+
+    fn try_visit_forloop_expr(&self, expr: &Expr) -> ControlFlow<Self::Break> {
+        try_visit_forloop_expr(self, expr)
+    }
+
+    /// A if/elif/else block, WS for the if/elif/else is directly in the struct
+    fn try_visit_if(&self, if_: &If, ws: &WS) -> ControlFlow<Self::Break> {
+        try_visit_if(self, if_, ws)
+    }
+
+    fn try_visit_if_expr(&self, expr: &Expr) -> ControlFlow<Self::Break> {
+        try_visit_if_expr(self, expr)
+    }
+
+    fn try_visit_else_if_expr(&self, expr: &Expr) -> ControlFlow<Self::Break> {
+        try_visit_else_if_expr(self, expr)
+    }
+
+    /// The `{% break %}` tag
+    fn try_visit_break(&self, ws: &WS) -> ControlFlow<Self::Break> {
+        try_visit_break(self, ws)
+    }
+
+    /// The `{% continue %}` tag
+    fn try_visit_continue(&self, ws: &WS) -> ControlFlow<Self::Break> {
+        try_visit_continue(self, ws)
+    }
+
+    /// The `{# #} `comment tag and its content
+    fn try_visit_comment(&self, ws: &WS, content: &str) -> ControlFlow<Self::Break> {
+        try_visit_comment(self, ws, content)
+    }
+
+    fn try_visit_expr(&self, expr: &Expr) -> ControlFlow<Self::Break> {
+        try_visit_expr(self, expr)
+    }
+
+    fn try_visit_expr_val(&self, expr_val: &ExprVal) -> ControlFlow<Self::Break> {
+        try_visit_expr_val(self, expr_val)
+    }
+
+    fn try_visit_string_expr_val(&self, s: &str) -> ControlFlow<Self::Break> {
+        try_visit_string_expr_val(self, s)
+    }
+
+    fn try_visit_int_expr_val(&self, i: i64) -> ControlFlow<Self::Break> {
+        try_visit_int_expr_val(self, i)
+    }
+
+    fn try_visit_float_expr_val(&self, f: f64) -> ControlFlow<Self::Break> {
+        try_visit_float_expr_val(self, f)
+    }
+
+    fn try_visit_bool_expr_val(&self, b: bool) -> ControlFlow<Self::Break> {
+        try_visit_bool_expr_val(self, b)
+    }
+
+    fn try_visit_ident_expr_val(&self, ident: &str) -> ControlFlow<Self::Break> {
+        try_visit_ident_expr_val(self, ident)
+    }
+
+    fn try_visit_math_expr_val(&self, math_expr: &MathExpr) -> ControlFlow<Self::Break> {
+        try_visit_math_expr_val(self, math_expr)
+    }
+
+    /// The left hand side of a math expression, `a` in `a + b`
+    fn try_visit_math_lhs_expr(&self, expr: &Expr) -> ControlFlow<Self::Break> {
+        try_visit_math_lhs_expr(self, expr)
+    }
+
+    /// The right hand side of a math expression, `b` in `a + b`
+    fn try_visit_math_rhs_expr(&self, expr: &Expr) -> ControlFlow<Self::Break> {
+        try_visit_math_rhs_expr(self, expr)
+    }
+
+    fn try_visit_logic_expr_val(&self, logic_expr: &LogicExpr) -> ControlFlow<Self::Break> {
+        try_visit_logic_expr_val(self, logic_expr)
+    }
+
+    /// The left hand side of a logic expression, `a` in `a and b`
+    fn try_visit_logic_lhs_expr(&self, expr: &Expr) -> ControlFlow<Self::Break> {
+        try_visit_logic_lhs_expr(self, expr)
+    }
+
+    /// The right hand side of a logic expression, `b` in `a and b`
+    fn try_visit_logic_rhs_expr(&self, expr: &Expr) -> ControlFlow<Self::Break> {
+        try_visit_logic_rhs_expr(self, expr)
+    }
+
+    fn try_visit_test_expr_val(&self, test: &Test) -> ControlFlow<Self::Break> {
+        try_visit_test_expr_val(self, test)
+    }
+
+    fn try_visit_array_expr_val(&self, array: &Vec<Expr>) -> ControlFlow<Self::Break> {
+        try_visit_array_expr_val(self, array)
+    }
+
+    fn try_visit_string_concat_expr_val(
+        &self,
+        string_concat: &StringConcat,
+    ) -> ControlFlow<Self::Break> {
+        try_visit_string_concat_expr_val(self, string_concat)
+    }
+
+    /// Any of the values concatenated with `~`
+    fn try_visit_string_concat_value(&self, expr_val: &ExprVal) -> ControlFlow<Self::Break> {
+        try_visit_string_concat_value(self, expr_val)
+    }
+
+    fn try_visit_macro_call(&self, macro_call: &MacroCall) -> ControlFlow<Self::Break> {
+        try_visit_macro_call(self, macro_call)
+    }
+
+    fn try_visit_function_call(&self, function_call: &FunctionCall) -> ControlFlow<Self::Break> {
+        try_visit_function_call(self, function_call)
+    }
+
+    fn try_visit_in_expr_val(&self, in_: &In) -> ControlFlow<Self::Break> {
+        try_visit_in_expr_val(self, in_)
+    }
+
+    /// The needle, `a` in `a in b`
+    fn try_visit_in_lhs_expr(&self, expr: &Expr) -> ControlFlow<Self::Break> {
+        try_visit_in_lhs_expr(self, expr)
+    }
+
+    /// The haystack, `b` in `a in b`
+    fn try_visit_in_rhs_expr(&self, expr: &Expr) -> ControlFlow<Self::Break> {
+        try_visit_in_rhs_expr(self, expr)
+    }
+}
+
+// Whole AST
+pub fn try_visit_ast<V: TryVisitor + ?Sized>(visitor: &V, ast: &Ast) -> ControlFlow<V::Break> {
+    for node in ast {
+        match node {
+            Node::Super => visitor.try_visit_super()?,
+            Node::Text(text) => visitor.try_visit_text(text)?,
+            Node::VariableBlock(ws, expr) => visitor.try_visit_variable_block(ws, expr)?,
+            Node::MacroDefinition(left_ws, macro_definition, right_ws) => {
+                visitor.try_visit_macro_definition(left_ws, macro_definition, right_ws)?;
+            }
+            Node::Extends(ws, content) => visitor.try_visit_extends(ws, content)?,
+            Node::Include(ws, includes, ignore_missing) => {
+                visitor.try_visit_include(ws, includes, *ignore_missing)?;
+            }
+            Node::ImportMacro(ws, path, name) => visitor.try_visit_import_macro(ws, path, name)?,
+            Node::Set(ws, set) => visitor.try_visit_set(ws, set)?,
+            Node::Raw(left_ws, content, right_ws) => {
+                visitor.try_visit_raw(left_ws, content, right_ws)?;
+            }
+            Node::FilterSection(left_ws, filter_section, right_ws) => {
+                visitor.try_visit_filter_section(left_ws, filter_section, right_ws)?;
+            }
+            Node::Block(left_ws, block, right_ws) => {
+                visitor.try_visit_block(left_ws, block, right_ws)?;
+            }
+            Node::Forloop(left_ws, forloop, right_ws) => {
+                visitor.try_visit_forloop(left_ws, forloop, right_ws)?;
+            }
+            Node::If(if_, ws) => visitor.try_visit_if(if_, ws)?,
+            Node::Break(ws) => visitor.try_visit_break(ws)?,
+            Node::Continue(ws) => visitor.try_visit_continue(ws)?,
+            Node::Comment(ws, content) => visitor.try_visit_comment(ws, content)?,
+        }
+    }
+
+    ControlFlow::Continue(())
+}
+
+/// A call to `{{ super() }}` in a block
+pub fn try_visit_super<V: TryVisitor + ?Sized>(_visitor: &V) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+/// Some actual text
+pub fn try_visit_text<V: TryVisitor + ?Sized>(_visitor: &V, _text: &str) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+/// A `{{ }}` block
+pub fn try_visit_variable_block<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    _ws: &WS,
+    expr: &Expr,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_expr(expr)
+}
+
+/// A `{% macro hello() %}...{% endmacro %}`
+pub fn try_visit_macro_definition<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    _left_ws: &WS,
+    macro_definition: &MacroDefinition,
+    _right_ws: &WS,
+) -> ControlFlow<V::Break> {
+    with_ancestor(
+        visitor,
+        || Ancestor::MacroDefinition {
+            name: macro_definition.name.clone(),
+        },
+        || {
+            visitor.try_visit_ast(&macro_definition.body)?;
+
+            for expr in macro_definition.args.values().flatten() {
+                visitor.try_visit_expr(expr)?;
+            }
+
+            ControlFlow::Continue(())
+        },
+    )
+}
+
+/// The `{% extends "blabla.html" %}` node, contains the template name
+pub fn try_visit_extends<V: TryVisitor + ?Sized>(
+    _visitor: &V,
+    _ws: &WS,
+    _content: &str,
+) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+/// The `{% include "blabla.html" %}` node, contains the template name
+pub fn try_visit_include<V: TryVisitor + ?Sized>(
+    _visitor: &V,
+    _ws: &WS,
+    _includes: &[String],
+    _ignore_missing: bool,
+) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+/// The `{% import "macros.html" as macros %}`
+pub fn try_visit_import_macro<V: TryVisitor + ?Sized>(
+    _visitor: &V,
+    _ws: &WS,
+    _path: &str,
+    _name: &str,
+) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+/// The `{% set val = something %}` tag
+pub fn try_visit_set<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    _ws: &WS,
+    set: &Set,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_expr(&set.value)
+}
+
+/// The text between `{% raw %}` and `{% endraw %}`
+pub fn try_visit_raw<V: TryVisitor + ?Sized>(
+    _visitor: &V,
+    _left_ws: &WS,
+    _content: &str,
+    _right_ws: &WS,
+) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+/// A filter section node `{{ filter name(param="value") }} content {{ endfilter }}`
+pub fn try_visit_filter_section<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    _left_ws: &WS,
+    filter_section: &FilterSection,
+    _right_ws: &WS,
+) -> ControlFlow<V::Break> {
+    with_ancestor(
+        visitor,
+        || Ancestor::FilterSection {
+            name: filter_section.filter.name.clone(),
+        },
+        || visitor.try_visit_ast(&filter_section.body),
+    )?;
+
+    visitor.try_visit_function_call(&filter_section.filter)
+}
+
+/// A `{% block name %}...{% endblock %}`
+pub fn try_visit_block<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    _left_ws: &WS,
+    block: &Block,
+    _right_ws: &WS,
+) -> ControlFlow<V::Break> {
+    with_ancestor(
+        visitor,
+        || Ancestor::Block {
+            name: block.name.clone(),
+        },
+        || visitor.try_visit_ast(&block.body),
+    )
+}
+
+/// A `{% for i in items %}...{% endfor %}`
+pub fn try_visit_forloop<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    _left_ws: &WS,
+    forloop: &Forloop,
+    _right_ws: &WS,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_forloop_expr(&forloop.container)?;
+
+    with_ancestor(
+        visitor,
+        || Ancestor::Forloop {
+            key: forloop.key.clone(),
+            value: forloop.value.clone(),
+        },
+        || visitor.try_visit_ast(&forloop.body),
+    )?;
+
+    if let Some(empty_body) = &forloop.empty_body {
+        with_ancestor(
+            visitor,
+            || Ancestor::ForloopEmpty,
+            || visitor.try_visit_ast(empty_body),
+        )?;
+    }
+
+    ControlFlow::Continue(())
+}
+
+pub fn try_visit_forloop_expr<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    expr: &Expr,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_expr(expr)
+}
+
+/// A if/elif/else block, WS for the if/elif/else is directly in the struct
+pub fn try_visit_if<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    if_: &If,
+    _ws: &WS,
+) -> ControlFlow<V::Break> {
+    for (index, (_, expr, ast)) in if_.conditions.iter().enumerate() {
+        with_ancestor(
+            visitor,
+            || Ancestor::If { branch: index },
+            || {
+                if index == 0 {
+                    visitor.try_visit_if_expr(expr)?;
+                } else {
+                    visitor.try_visit_else_if_expr(expr)?;
+                }
+
+                visitor.try_visit_ast(ast)
+            },
+        )?;
+    }
+
+    if let Some((_, ast)) = &if_.otherwise {
+        with_ancestor(
+            visitor,
+            || Ancestor::IfOtherwise,
+            || visitor.try_visit_ast(ast),
+        )?;
+    }
+
+    ControlFlow::Continue(())
+}
+
+pub fn try_visit_if_expr<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    expr: &Expr,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_expr(expr)
+}
+
+pub fn try_visit_else_if_expr<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    expr: &Expr,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_expr(expr)
+}
+
+/// The `{% break %}` tag
+pub fn try_visit_break<V: TryVisitor + ?Sized>(_visitor: &V, _ws: &WS) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+/// The `{% continue %}` tag
+pub fn try_visit_continue<V: TryVisitor + ?Sized>(_visitor: &V, _ws: &WS) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+/// The `{# #} `comment tag and its content
+pub fn try_visit_comment<V: TryVisitor + ?Sized>(
+    _visitor: &V,
+    _ws: &WS,
+    _content: &str,
+) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+pub fn try_visit_expr<V: TryVisitor + ?Sized>(visitor: &V, expr: &Expr) -> ControlFlow<V::Break> {
+    for function_call in &expr.filters {
+        visitor.try_visit_function_call(function_call)?;
+    }
+
+    visitor.try_visit_expr_val(&expr.val)
+}
+
+pub fn try_visit_expr_val<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    expr_val: &ExprVal,
+) -> ControlFlow<V::Break> {
+    match expr_val {
+        ExprVal::String(s) => visitor.try_visit_string_expr_val(s),
+        ExprVal::Int(i) => visitor.try_visit_int_expr_val(*i),
+        ExprVal::Float(f) => visitor.try_visit_float_expr_val(*f),
+        ExprVal::Bool(b) => visitor.try_visit_bool_expr_val(*b),
+        ExprVal::Ident(ident) => visitor.try_visit_ident_expr_val(ident),
+        ExprVal::Math(math_expr) => visitor.try_visit_math_expr_val(math_expr),
+        ExprVal::Logic(logic_expr) => visitor.try_visit_logic_expr_val(logic_expr),
+        ExprVal::Test(test) => visitor.try_visit_test_expr_val(test),
+        ExprVal::MacroCall(macro_call) => visitor.try_visit_macro_call(macro_call),
+        ExprVal::FunctionCall(function_call) => visitor.try_visit_function_call(function_call),
+        ExprVal::Array(array) => visitor.try_visit_array_expr_val(array),
+        ExprVal::StringConcat(string_concat) => {
+            visitor.try_visit_string_concat_expr_val(string_concat)
+        }
+        ExprVal::In(in_) => visitor.try_visit_in_expr_val(in_),
+    }
+}
+
+pub fn try_visit_string_expr_val<V: TryVisitor + ?Sized>(
+    _visitor: &V,
+    _s: &str,
+) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+pub fn try_visit_int_expr_val<V: TryVisitor + ?Sized>(
+    _visitor: &V,
+    _i: i64,
+) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+pub fn try_visit_float_expr_val<V: TryVisitor + ?Sized>(
+    _visitor: &V,
+    _f: f64,
+) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+pub fn try_visit_bool_expr_val<V: TryVisitor + ?Sized>(
+    _visitor: &V,
+    _b: bool,
+) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+pub fn try_visit_ident_expr_val<V: TryVisitor + ?Sized>(
+    _visitor: &V,
+    _ident: &str,
+) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+pub fn try_visit_math_expr_val<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    math_expr: &MathExpr,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_math_lhs_expr(&math_expr.lhs)?;
+    visitor.try_visit_math_rhs_expr(&math_expr.rhs)
+}
+
+/// The left hand side of a math expression, `a` in `a + b`
+pub fn try_visit_math_lhs_expr<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    expr: &Expr,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_expr(expr)
+}
+
+/// The right hand side of a math expression, `b` in `a + b`
+pub fn try_visit_math_rhs_expr<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    expr: &Expr,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_expr(expr)
+}
+
+pub fn try_visit_logic_expr_val<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    logic_expr: &LogicExpr,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_logic_lhs_expr(&logic_expr.lhs)?;
+    visitor.try_visit_logic_rhs_expr(&logic_expr.rhs)
+}
+
+/// The left hand side of a logic expression, `a` in `a and b`
+pub fn try_visit_logic_lhs_expr<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    expr: &Expr,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_expr(expr)
+}
+
+/// The right hand side of a logic expression, `b` in `a and b`
+pub fn try_visit_logic_rhs_expr<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    expr: &Expr,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_expr(expr)
+}
+
+pub fn try_visit_test_expr_val<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    test: &Test,
+) -> ControlFlow<V::Break> {
+    for expr in &test.args {
+        visitor.try_visit_expr(expr)?;
+    }
+
+    ControlFlow::Continue(())
+}
+
+pub fn try_visit_array_expr_val<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    array: &Vec<Expr>,
+) -> ControlFlow<V::Break> {
+    for expr in array {
+        visitor.try_visit_expr(expr)?;
+    }
+
+    ControlFlow::Continue(())
+}
+
+pub fn try_visit_string_concat_expr_val<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    string_concat: &StringConcat,
+) -> ControlFlow<V::Break> {
+    for expr_val in &string_concat.values {
+        visitor.try_visit_string_concat_value(expr_val)?;
+    }
+
+    ControlFlow::Continue(())
+}
+
+/// Any of the values concatenated with `~`
+pub fn try_visit_string_concat_value<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    expr_val: &ExprVal,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_expr_val(expr_val)
+}
+
+pub fn try_visit_macro_call<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    macro_call: &MacroCall,
+) -> ControlFlow<V::Break> {
+    for expr in macro_call.args.values() {
+        visitor.try_visit_expr(expr)?;
+    }
+
+    ControlFlow::Continue(())
+}
+
+pub fn try_visit_function_call<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    function_call: &FunctionCall,
+) -> ControlFlow<V::Break> {
+    for expr in function_call.args.values() {
+        visitor.try_visit_expr(expr)?;
+    }
+
+    ControlFlow::Continue(())
+}
+
+pub fn try_visit_in_expr_val<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    in_: &In,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_in_lhs_expr(&in_.lhs)?;
+    visitor.try_visit_in_rhs_expr(&in_.rhs)
+}
+
+/// The needle, `a` in `a in b`
+pub fn try_visit_in_lhs_expr<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    expr: &Expr,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_expr(expr)
+}
+
+/// The haystack, `b` in `a in b`
+pub fn try_visit_in_rhs_expr<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    expr: &Expr,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_expr(expr)
+}
+/// Same as [`crate::VisitorMut`], but any method can stop the visit early by returning [`ControlFlow::Break`]
+pub trait TryVisitorMut {
+    type Break;
+
+    /// The ancestors of the visited node, kept up to date by the walkers if some storage is returned
+    fn ancestry_mut(&mut self) -> Option<&mut Ancestry> {
+        None
+    }
+
+    // Whole AST
+    fn try_visit_ast_mut(&mut self, ast: &Ast) -> ControlFlow<Self::Break> {
+        try_visit_ast_mut(self, ast)
+    }
+
+    /// A call to `{{ super() }}` in a block
+    fn try_visit_super_mut(&mut self) -> ControlFlow<Self::Break> {
+        try_visit_super_mut(self)
+    }
+
+    /// Some actual text
+    fn try_visit_text_mut(&mut self, text: &str) -> ControlFlow<Self::Break> {
+        try_visit_text_mut(self, text)
+    }
+
+    /// A `{{ }}` block
+    fn try_visit_variable_block_mut(&mut self, ws: &WS, expr: &Expr) -> ControlFlow<Self::Break> {
+        try_visit_variable_block_mut(self, ws, expr)
+    }
+
+    /// A `{% macro hello() %}...{% endmacro %}`
+    fn try_visit_macro_definition_mut(
+        &mut self,
+        left_ws: &WS,
+        macro_definition: &MacroDefinition,
+        right_ws: &WS,
+    ) -> ControlFlow<Self::Break> {
+        try_visit_macro_definition_mut(self, left_ws, macro_definition, right_ws)
+    }
+
+    /// The `{% extends "blabla.html" %}` node, contains the template name
+    fn try_visit_extends_mut(&mut self, ws: &WS, content: &str) -> ControlFlow<Self::Break> {
+        try_visit_extends_mut(self, ws, content)
+    }
+
+    /// The `{% include "blabla.html" %}` node, contains the template name
+    fn try_visit_include_mut(
+        &mut self,
+        ws: &WS,
+        includes: &[String],
+        ignore_missing: bool,
+    ) -> ControlFlow<Self::Break> {
+        try_visit_include_mut(self, ws, includes, ignore_missing)
+    }
+
+    /// The `{% import "macros.html" as macros %}`
+    fn try_visit_import_macro_mut(
+        &mut self,
+        ws: &WS,
+        path: &str,
+        name: &str,
+    ) -> ControlFlow<Self::Break> {
+        try_visit_import_macro_mut(self, ws, path, name)
+    }
+
+    /// The `{% set val = something %}` tag
+    fn try_visit_set_mut(&mut self, ws: &WS, set: &Set) -> ControlFlow<Self::Break> {
+        try_visit_set_mut(self, ws, set)
+    }
+
+    /// The text between `{% raw %}` and `{% endraw %}`
+    fn try_visit_raw_mut(
+        &mut self,
+        left_ws: &WS,
+        content: &str,
+        right_ws: &WS,
+    ) -> ControlFlow<Self::Break> {
+        try_visit_raw_mut(self, left_ws, content, right_ws)
+    }
+
+    /// A filter section node `{{ filter name(param="value") }} content {{ endfilter }}`
+    fn try_visit_filter_section_mut(
+        &mut self,
+        left_ws: &WS,
+        filter_section: &FilterSection,
+        right_ws: &WS,
+    ) -> ControlFlow<Self::Break> {
+        try_visit_filter_section_mut(self, left_ws, filter_section, right_ws)
+    }
+
+    /// A `{% block name %}...{% endblock %}`
+    fn try_visit_block_mut(
+        &mut self,
+        left_ws: &WS,
+        block: &Block,
+        right_ws: &WS,
+    ) -> ControlFlow<Self::Break> {
+        try_visit_block_mut(self, left_ws, block, right_ws)
+    }
+
+    /// A `{% for i in items %}...{% endfor %}`
+    fn try_visit_forloop_mut(
+        &mut self,
+        left_ws: &WS,
+        forloop: &Forloop,
+        right_ws: &WS,
+    ) -> ControlFlow<Self::Break> {
+        try_visit_forloop_mut(self, left_ws, forloop, right_ws)
+    }
+
+    fn try_visit_forloop_expr_mut(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+        try_visit_forloop_expr_mut(self, expr)
+    }
+
+    /// A if/elif/else block, WS for the if/elif/else is directly in the struct
+    fn try_visit_if_mut(&mut self, if_: &If, ws: &WS) -> ControlFlow<Self::Break> {
+        try_visit_if_mut(self, if_, ws)
+    }
+
+    fn try_visit_if_expr_mut(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+        try_visit_if_expr_mut(self, expr)
+    }
+
+    fn try_visit_else_if_expr_mut(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+        try_visit_else_if_expr_mut(self, expr)
+    }
+
+    /// The `{% break %}` tag
+    fn try_visit_break_mut(&mut self, ws: &WS) -> ControlFlow<Self::Break> {
+        try_visit_break_mut(self, ws)
+    }
+
+    /// The `{% continue %}` tag
+    fn try_visit_continue_mut(&mut self, ws: &WS) -> ControlFlow<Self::Break> {
+        try_visit_continue_mut(self, ws)
+    }
+
+    /// The `{# #} `comment tag and its content
+    fn try_visit_comment_mut(&mut self, ws: &WS, content: &str) -> ControlFlow<Self::Break> {
+        try_visit_comment_mut(self, ws, content)
+    }
+
+    fn try_visit_expr_mut(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+        try_visit_expr_mut(self, expr)
+    }
+
+    fn try_visit_expr_val_mut(&mut self, expr_val: &ExprVal) -> ControlFlow<Self::Break> {
+        try_visit_expr_val_mut(self, expr_val)
+    }
+
+    fn try_visit_string_expr_val_mut(&mut self, s: &str) -> ControlFlow<Self::Break> {
+        try_visit_string_expr_val_mut(self, s)
+    }
+
+    fn try_visit_int_expr_val_mut(&mut self, i: i64) -> ControlFlow<Self::Break> {
+        try_visit_int_expr_val_mut(self, i)
+    }
+
+    fn try_visit_float_expr_val_mut(&mut self, f: f64) -> ControlFlow<Self::Break> {
+        try_visit_float_expr_val_mut(self, f)
+    }
+
+    fn try_visit_bool_expr_val_mut(&mut self, b: bool) -> ControlFlow<Self::Break> {
+        try_visit_bool_expr_val_mut(self, b)
+    }
+
+    fn try_visit_ident_expr_val_mut(&mut self, ident: &str) -> ControlFlow<Self::Break> {
+        try_visit_ident_expr_val_mut(self, ident)
+    }
+
+    fn try_visit_math_expr_val_mut(&mut self, math_expr: &MathExpr) -> ControlFlow<Self::Break> {
+        try_visit_math_expr_val_mut(self, math_expr)
+    }
+
+    /// The left hand side of a math expression, `a` in `a + b`
+    fn try_visit_math_lhs_expr_mut(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+        try_visit_math_lhs_expr_mut(self, expr)
+    }
+
+    /// The right hand side of a math expression, `b` in `a + b`
+    fn try_visit_math_rhs_expr_mut(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+        try_visit_math_rhs_expr_mut(self, expr)
+    }
+
+    fn try_visit_logic_expr_val_mut(&mut self, logic_expr: &LogicExpr) -> ControlFlow<Self::Break> {
+        try_visit_logic_expr_val_mut(self, logic_expr)
+    }
+
+    /// The left hand side of a logic expression, `a` in `a and b`
+    fn try_visit_logic_lhs_expr_mut(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+        try_visit_logic_lhs_expr_mut(self, expr)
+    }
+
+    /// The right hand side of a logic expression, `b` in `a and b`
+    fn try_visit_logic_rhs_expr_mut(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+        try_visit_logic_rhs_expr_mut(self, expr)
+    }
+
+    fn try_visit_test_expr_val_mut(&mut self, test: &Test) -> ControlFlow<Self::Break> {
+        try_visit_test_expr_val_mut(self, test)
+    }
+
+    fn try_visit_array_expr_val_mut(&mut self, array: &Vec<Expr>) -> ControlFlow<Self::Break> {
+        try_visit_array_expr_val_mut(self, array)
+    }
+
+    fn try_visit_string_concat_expr_val_mut(
+        &mut self,
+        string_concat: &StringConcat,
+    ) -> ControlFlow<Self::Break> {
+        try_visit_string_concat_expr_val_mut(self, string_concat)
+    }
+
+    /// Any of the values concatenated with `~`
+    fn try_visit_string_concat_value_mut(
+        &mut self,
+        expr_val: &ExprVal,
+    ) -> ControlFlow<Self::Break> {
+        try_visit_string_concat_value_mut(self, expr_val)
+    }
+
+    fn try_visit_macro_call_mut(&mut self, macro_call: &MacroCall) -> ControlFlow<Self::Break> {
+        try_visit_macro_call_mut(self, macro_call)
+    }
+
+    fn try_visit_function_call_mut(
+        &mut self,
+        function_call: &FunctionCall,
+    ) -> ControlFlow<Self::Break> {
+        try_visit_function_call_mut(self, function_call)
+    }
+
+    fn try_visit_in_expr_val_mut(&mut self, in_: &In) -> ControlFlow<Self::Break> {
+        try_visit_in_expr_val_mut(self, in_)
+    }
+
+    /// The needle, `a` in `a in b`
+    fn try_visit_in_lhs_expr_mut(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+        try_visit_in_lhs_expr_mut(self, expr)
+    }
+
+    /// The haystack, `b` in `a in b`
+    fn try_visit_in_rhs_expr_mut(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+        try_visit_in_rhs_expr_mut(self, expr)
+    }
+}
+
+// Whole AST
+pub fn try_visit_ast_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    ast: &Ast,
+) -> ControlFlow<V::Break> {
+    for node in ast {
+        match node {
+            Node::Super => visitor.try_visit_super_mut()?,
+            Node::Text(text) => visitor.try_visit_text_mut(text)?,
+            Node::VariableBlock(ws, expr) => visitor.try_visit_variable_block_mut(ws, expr)?,
+            Node::MacroDefinition(left_ws, macro_definition, right_ws) => {
+                visitor.try_visit_macro_definition_mut(left_ws, macro_definition, right_ws)?;
+            }
+            Node::Extends(ws, content) => visitor.try_visit_extends_mut(ws, content)?,
+            Node::Include(ws, includes, ignore_missing) => {
+                visitor.try_visit_include_mut(ws, includes, *ignore_missing)?;
+            }
+            Node::ImportMacro(ws, path, name) => {
+                visitor.try_visit_import_macro_mut(ws, path, name)?;
+            }
+            Node::Set(ws, set) => visitor.try_visit_set_mut(ws, set)?,
+            Node::Raw(left_ws, content, right_ws) => {
+                visitor.try_visit_raw_mut(left_ws, content, right_ws)?;
+            }
+            Node::FilterSection(left_ws, filter_section, right_ws) => {
+                visitor.try_visit_filter_section_mut(left_ws, filter_section, right_ws)?;
+            }
+            Node::Block(left_ws, block, right_ws) => {
+                visitor.try_visit_block_mut(left_ws, block, right_ws)?;
+            }
+            Node::Forloop(left_ws, forloop, right_ws) => {
+                visitor.try_visit_forloop_mut(left_ws, forloop, right_ws)?;
+            }
+            Node::If(if_, ws) => visitor.try_visit_if_mut(if_, ws)?,
+            Node::Break(ws) => visitor.try_visit_break_mut(ws)?,
+            Node::Continue(ws) => visitor.try_visit_continue_mut(ws)?,
+            Node::Comment(ws, content) => visitor.try_visit_comment_mut(ws, content)?,
+        }
+    }
+
+    ControlFlow::Continue(())
+}
+
+/// A call to `{{ super() }}` in a block
+pub fn try_visit_super_mut<V: TryVisitorMut + ?Sized>(_visitor: &mut V) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+/// Some actual text
+pub fn try_visit_text_mut<V: TryVisitorMut + ?Sized>(
+    _visitor: &mut V,
+    _text: &str,
+) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+/// A `{{ }}` block
+pub fn try_visit_variable_block_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    _ws: &WS,
+    expr: &Expr,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_expr_mut(expr)
+}
+
+/// A `{% macro hello() %}...{% endmacro %}`
+pub fn try_visit_macro_definition_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    _left_ws: &WS,
+    macro_definition: &MacroDefinition,
+    _right_ws: &WS,
+) -> ControlFlow<V::Break> {
+    with_ancestor_mut(
+        visitor,
+        || Ancestor::MacroDefinition {
+            name: macro_definition.name.clone(),
+        },
+        |visitor| {
+            visitor.try_visit_ast_mut(&macro_definition.body)?;
+
+            for expr in macro_definition.args.values().flatten() {
+                visitor.try_visit_expr_mut(expr)?;
+            }
+
+            ControlFlow::Continue(())
+        },
+    )
+}
+
+/// The `{% extends "blabla.html" %}` node, contains the template name
+pub fn try_visit_extends_mut<V: TryVisitorMut + ?Sized>(
+    _visitor: &mut V,
+    _ws: &WS,
+    _content: &str,
+) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+/// The `{% include "blabla.html" %}` node, contains the template name
+pub fn try_visit_include_mut<V: TryVisitorMut + ?Sized>(
+    _visitor: &mut V,
+    _ws: &WS,
+    _includes: &[String],
+    _ignore_missing: bool,
+) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+/// The `{% import "macros.html" as macros %}`
+pub fn try_visit_import_macro_mut<V: TryVisitorMut + ?Sized>(
+    _visitor: &mut V,
+    _ws: &WS,
+    _path: &str,
+    _name: &str,
+) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+/// The `{% set val = something %}` tag
+pub fn try_visit_set_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    _ws: &WS,
+    set: &Set,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_expr_mut(&set.value)
+}
+
+/// The text between `{% raw %}` and `{% endraw %}`
+pub fn try_visit_raw_mut<V: TryVisitorMut + ?Sized>(
+    _visitor: &mut V,
+    _left_ws: &WS,
+    _content: &str,
+    _right_ws: &WS,
+) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+/// A filter section node `{{ filter name(param="value") }} content {{ endfilter }}`
+pub fn try_visit_filter_section_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    _left_ws: &WS,
+    filter_section: &FilterSection,
+    _right_ws: &WS,
+) -> ControlFlow<V::Break> {
+    with_ancestor_mut(
+        visitor,
+        || Ancestor::FilterSection {
+            name: filter_section.filter.name.clone(),
+        },
+        |visitor| visitor.try_visit_ast_mut(&filter_section.body),
+    )?;
+
+    visitor.try_visit_function_call_mut(&filter_section.filter)
+}
+
+/// A `{% block name %}...{% endblock %}`
+pub fn try_visit_block_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    _left_ws: &WS,
+    block: &Block,
+    _right_ws: &WS,
+) -> ControlFlow<V::Break> {
+    with_ancestor_mut(
+        visitor,
+        || Ancestor::Block {
+            name: block.name.clone(),
+        },
+        |visitor| visitor.try_visit_ast_mut(&block.body),
+    )
+}
+
+/// A `{% for i in items %}...{% endfor %}`
+pub fn try_visit_forloop_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    _left_ws: &WS,
+    forloop: &Forloop,
+    _right_ws: &WS,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_forloop_expr_mut(&forloop.container)?;
+
+    with_ancestor_mut(
+        visitor,
+        || Ancestor::Forloop {
+            key: forloop.key.clone(),
+            value: forloop.value.clone(),
+        },
+        |visitor| visitor.try_visit_ast_mut(&forloop.body),
+    )?;
+
+    if let Some(empty_body) = &forloop.empty_body {
+        with_ancestor_mut(
+            visitor,
+            || Ancestor::ForloopEmpty,
+            |visitor| visitor.try_visit_ast_mut(empty_body),
+        )?;
+    }
+
+    ControlFlow::Continue(())
+}
+
+pub fn try_visit_forloop_expr_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    expr: &Expr,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_expr_mut(expr)
+}
+
+/// A if/elif/else block, WS for the if/elif/else is directly in the struct
+pub fn try_visit_if_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    if_: &If,
+    _ws: &WS,
+) -> ControlFlow<V::Break> {
+    for (index, (_, expr, ast)) in if_.conditions.iter().enumerate() {
+        with_ancestor_mut(
+            visitor,
+            || Ancestor::If { branch: index },
+            |visitor| {
+                if index == 0 {
+                    visitor.try_visit_if_expr_mut(expr)?;
+                } else {
+                    visitor.try_visit_else_if_expr_mut(expr)?;
+                }
+
+                visitor.try_visit_ast_mut(ast)
+            },
+        )?;
+    }
+
+    if let Some((_, ast)) = &if_.otherwise {
+        with_ancestor_mut(
+            visitor,
+            || Ancestor::IfOtherwise,
+            |visitor| visitor.try_visit_ast_mut(ast),
+        )?;
+    }
+
+    ControlFlow::Continue(())
+}
+
+pub fn try_visit_if_expr_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    expr: &Expr,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_expr_mut(expr)
+}
+
+pub fn try_visit_else_if_expr_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    expr: &Expr,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_expr_mut(expr)
+}
+
+/// The `{% break %}` tag
+pub fn try_visit_break_mut<V: TryVisitorMut + ?Sized>(
+    _visitor: &mut V,
+    _ws: &WS,
+) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+/// The `{% continue %}` tag
+pub fn try_visit_continue_mut<V: TryVisitorMut + ?Sized>(
+    _visitor: &mut V,
+    _ws: &WS,
+) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+/// The `{# #} `comment tag and its content
+pub fn try_visit_comment_mut<V: TryVisitorMut + ?Sized>(
+    _visitor: &mut V,
+    _ws: &WS,
+    _content: &str,
+) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+pub fn try_visit_expr_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    expr: &Expr,
+) -> ControlFlow<V::Break> {
+    for function_call in &expr.filters {
+        visitor.try_visit_function_call_mut(function_call)?;
+    }
+
+    visitor.try_visit_expr_val_mut(&expr.val)
+}
+
+pub fn try_visit_expr_val_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    expr_val: &ExprVal,
+) -> ControlFlow<V::Break> {
+    match expr_val {
+        ExprVal::String(s) => visitor.try_visit_string_expr_val_mut(s),
+        ExprVal::Int(i) => visitor.try_visit_int_expr_val_mut(*i),
+        ExprVal::Float(f) => visitor.try_visit_float_expr_val_mut(*f),
+        ExprVal::Bool(b) => visitor.try_visit_bool_expr_val_mut(*b),
+        ExprVal::Ident(ident) => visitor.try_visit_ident_expr_val_mut(ident),
+        ExprVal::Math(math_expr) => visitor.try_visit_math_expr_val_mut(math_expr),
+        ExprVal::Logic(logic_expr) => visitor.try_visit_logic_expr_val_mut(logic_expr),
+        ExprVal::Test(test) => visitor.try_visit_test_expr_val_mut(test),
+        ExprVal::MacroCall(macro_call) => visitor.try_visit_macro_call_mut(macro_call),
+        ExprVal::FunctionCall(function_call) => visitor.try_visit_function_call_mut(function_call),
+        ExprVal::Array(array) => visitor.try_visit_array_expr_val_mut(array),
+        ExprVal::StringConcat(string_concat) => {
+            visitor.try_visit_string_concat_expr_val_mut(string_concat)
+        }
+        ExprVal::In(in_) => visitor.try_visit_in_expr_val_mut(in_),
+    }
+}
+
+pub fn try_visit_string_expr_val_mut<V: TryVisitorMut + ?Sized>(
+    _visitor: &mut V,
+    _s: &str,
+) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+pub fn try_visit_int_expr_val_mut<V: TryVisitorMut + ?Sized>(
+    _visitor: &mut V,
+    _i: i64,
+) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+pub fn try_visit_float_expr_val_mut<V: TryVisitorMut + ?Sized>(
+    _visitor: &mut V,
+    _f: f64,
+) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+pub fn try_visit_bool_expr_val_mut<V: TryVisitorMut + ?Sized>(
+    _visitor: &mut V,
+    _b: bool,
+) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+pub fn try_visit_ident_expr_val_mut<V: TryVisitorMut + ?Sized>(
+    _visitor: &mut V,
+    _ident: &str,
+) -> ControlFlow<V::Break> {
+    ControlFlow::Continue(())
+}
+
+pub fn try_visit_math_expr_val_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    math_expr: &MathExpr,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_math_lhs_expr_mut(&math_expr.lhs)?;
+    visitor.try_visit_math_rhs_expr_mut(&math_expr.rhs)
+}
+
+/// The left hand side of a math expression, `a` in `a + b`
+pub fn try_visit_math_lhs_expr_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    expr: &Expr,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_expr_mut(expr)
+}
+
+/// The right hand side of a math expression, `b` in `a + b`
+pub fn try_visit_math_rhs_expr_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    expr: &Expr,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_expr_mut(expr)
+}
+
+pub fn try_visit_logic_expr_val_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    logic_expr: &LogicExpr,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_logic_lhs_expr_mut(&logic_expr.lhs)?;
+    visitor.try_visit_logic_rhs_expr_mut(&logic_expr.rhs)
+}
+
+/// The left hand side of a logic expression, `a` in `a and b`
+pub fn try_visit_logic_lhs_expr_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    expr: &Expr,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_expr_mut(expr)
+}
+
+/// The right hand side of a logic expression, `b` in `a and b`
+pub fn try_visit_logic_rhs_expr_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    expr: &Expr,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_expr_mut(expr)
+}
+
+pub fn try_visit_test_expr_val_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    test: &Test,
+) -> ControlFlow<V::Break> {
+    for expr in &test.args {
+        visitor.try_visit_expr_mut(expr)?;
+    }
+
+    ControlFlow::Continue(())
+}
+
+pub fn try_visit_array_expr_val_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    array: &Vec<Expr>,
+) -> ControlFlow<V::Break> {
+    for expr in array {
+        visitor.try_visit_expr_mut(expr)?;
+    }
+
+    ControlFlow::Continue(())
+}
+
+pub fn try_visit_string_concat_expr_val_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    string_concat: &StringConcat,
+) -> ControlFlow<V::Break> {
+    for expr_val in &string_concat.values {
+        visitor.try_visit_string_concat_value_mut(expr_val)?;
+    }
+
+    ControlFlow::Continue(())
+}
+
+/// Any of the values concatenated with `~`
+pub fn try_visit_string_concat_value_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    expr_val: &ExprVal,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_expr_val_mut(expr_val)
+}
+
+pub fn try_visit_macro_call_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    macro_call: &MacroCall,
+) -> ControlFlow<V::Break> {
+    for expr in macro_call.args.values() {
+        visitor.try_visit_expr_mut(expr)?;
+    }
+
+    ControlFlow::Continue(())
+}
+
+pub fn try_visit_function_call_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    function_call: &FunctionCall,
+) -> ControlFlow<V::Break> {
+    for expr in function_call.args.values() {
+        visitor.try_visit_expr_mut(expr)?;
+    }
+
+    ControlFlow::Continue(())
+}
+
+pub fn try_visit_in_expr_val_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    in_: &In,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_in_lhs_expr_mut(&in_.lhs)?;
+    visitor.try_visit_in_rhs_expr_mut(&in_.rhs)
+}
+
+/// The needle, `a` in `a in b`
+pub fn try_visit_in_lhs_expr_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    expr: &Expr,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_expr_mut(expr)
+}
+
+/// The haystack, `b` in `a in b`
+pub fn try_visit_in_rhs_expr_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    expr: &Expr,
+) -> ControlFlow<V::Break> {
+    visitor.try_visit_expr_mut(expr)
+}
+
+/// The ancestor is popped even if the visit is stopped early, the ancestry is only borrowed
+/// while the ancestor is pushed or popped, so the visitor can borrow it in between
+fn with_ancestor<V: TryVisitor + ?Sized>(
+    visitor: &V,
+    ancestor: impl FnOnce() -> Ancestor,
+    visit: impl FnOnce() -> ControlFlow<V::Break>,
+) -> ControlFlow<V::Break> {
+    if let Some(ancestry) = visitor.ancestry() {
+        ancestry.borrow_mut().push(ancestor());
+    }
+
+    let control_flow = visit();
+
+    if let Some(ancestry) = visitor.ancestry() {
+        ancestry.borrow_mut().pop();
+    }
+
+    control_flow
+}
+
+/// The ancestor is popped even if the visit is stopped early
+fn with_ancestor_mut<V: TryVisitorMut + ?Sized>(
+    visitor: &mut V,
+    ancestor: impl FnOnce() -> Ancestor,
+    visit: impl FnOnce(&mut V) -> ControlFlow<V::Break>,
+) -> ControlFlow<V::Break> {
+    if let Some(ancestry) = visitor.ancestry_mut() {
+        ancestry.push(ancestor());
+    }
+
+    let control_flow = visit(visitor);
+
+    if let Some(ancestry) = visitor.ancestry_mut() {
+        ancestry.pop();
+    }
+
+    control_flow
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use tera::Template;
+
+    use super::*;
+
+    fn parse(source: &str) -> Ast {
+        Template::new("", None, source).unwrap().ast
+    }
+
+    /// Stops at the first ident with the name, counting the idents visited
+    #[derive(Default)]
+    struct Find {
+        name: &'static str,
+        visited: Cell<usize>,
+    }
+
+    impl TryVisitor for Find {
+        type Break = usize;
+
+        fn try_visit_ident_expr_val(&self, ident: &str) -> ControlFlow<usize> {
+            self.visited.set(self.visited.get() + 1);
+
+            if ident == self.name {
+                ControlFlow::Break(self.visited.get())
+            } else {
+                ControlFlow::Continue(())
+            }
+        }
+    }
+
+    /// Stops at the first ident with the name, with its ancestors
+    struct FindAncestors {
+        name: &'static str,
+        ancestry: RefCell<Ancestry>,
+    }
+
+    impl TryVisitor for FindAncestors {
+        type Break = Vec<Ancestor>;
+
+        fn ancestry(&self) -> Option<&RefCell<Ancestry>> {
+            Some(&self.ancestry)
+        }
+
+        fn try_visit_ident_expr_val(&self, ident: &str) -> ControlFlow<Vec<Ancestor>> {
+            if ident == self.name {
+                ControlFlow::Break(self.ancestry.borrow().ancestors().to_vec())
+            } else {
+                ControlFlow::Continue(())
+            }
+        }
+    }
+
+    /// Same as `FindAncestors`, as a mutable visitor
+    struct FindMut {
+        name: &'static str,
+        ancestry: Ancestry,
+    }
+
+    impl TryVisitorMut for FindMut {
+        type Break = Vec<Ancestor>;
+
+        fn ancestry_mut(&mut self) -> Option<&mut Ancestry> {
+            Some(&mut self.ancestry)
+        }
+
+        fn try_visit_ident_expr_val_mut(&mut self, ident: &str) -> ControlFlow<Vec<Ancestor>> {
+            if ident == self.name {
+                ControlFlow::Break(self.ancestry.ancestors().to_vec())
+            } else {
+                ControlFlow::Continue(())
+            }
+        }
+    }
+
+    const SOURCE: &str =
+        "{{ a + b }}{% for x in c %}{% if d %}{{ e | default(value=f) }}{% endif %}{% endfor %}";
+
+    #[test]
+    fn stops_early() {
+        let ast = parse(SOURCE);
+        let find = Find {
+            name: "b",
+            ..Find::default()
+        };
+
+        assert_eq!(find.try_visit_ast(&ast), ControlFlow::Break(2));
+        assert_eq!(find.visited.get(), 2);
+    }
+
+    #[test]
+    fn visits_everything_without_break() {
+        let ast = parse(SOURCE);
+        let find = Find {
+            name: "z",
+            ..Find::default()
+        };
+
+        assert_eq!(find.try_visit_ast(&ast), ControlFlow::Continue(()));
+        assert_eq!(find.visited.get(), 6);
+    }
+
+    #[test]
+    fn stops_early_with_the_ancestry() {
+        let ast = parse(SOURCE);
+        let mut find = FindMut {
+            name: "f",
+            ancestry: Ancestry::new(),
+        };
+
+        assert_eq!(
+            find.try_visit_ast_mut(&ast),
+            ControlFlow::Break(vec![
+                Ancestor::Forloop {
+                    key: None,
+                    value: "x".to_string()
+                },
+                Ancestor::If { branch: 0 },
+            ])
+        );
+
+        find.name = "c";
+
+        assert_eq!(find.try_visit_ast_mut(&ast), ControlFlow::Break(vec![]));
+    }
+
+    #[test]
+    fn stops_early_with_the_ancestry_of_an_immutable_visitor() {
+        let ast = parse(SOURCE);
+        let find = FindAncestors {
+            name: "f",
+            ancestry: RefCell::default(),
+        };
+
+        assert_eq!(
+            find.try_visit_ast(&ast),
+            ControlFlow::Break(vec![
+                Ancestor::Forloop {
+                    key: None,
+                    value: "x".to_string()
+                },
+                Ancestor::If { branch: 0 },
+            ])
+        );
+        // The ancestors are popped when the visit stops early
+        assert!(find.ancestry.borrow().is_empty());
+    }
+}
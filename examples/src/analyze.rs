@@ -23,31 +23,7 @@ async fn main() -> Result<()> {
     )
     .await?;
 
-    let mut visitor = full_template.visit_renderer();
-
-    let sorted_idents = visitor.sorted_idents();
-    if !sorted_idents.is_empty() {
-        println!("Used variables:");
-        for ident in visitor.sorted_idents() {
-            println!("\t- {ident}");
-        }
-    }
-
-    let sorted_iterable_idents = visitor.sorted_iterable_idents();
-    if !sorted_iterable_idents.is_empty() {
-        println!("Iterable variables:");
-        for ident in visitor.sorted_iterable_idents() {
-            println!("\t- {ident}");
-        }
-    }
-
-    let sorted_optional_idents = visitor.sorted_optional_idents();
-    if !sorted_optional_idents.is_empty() {
-        println!("Potentially optional variables:");
-        for ident in visitor.sorted_optional_idents() {
-            println!("\t- {ident}");
-        }
-    }
+    print!("{}", full_template.analysis());
 
     Ok(())
 }